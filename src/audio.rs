@@ -0,0 +1,605 @@
+use std::collections::HashMap;
+use std::f64::consts::FRAC_PI_4;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::decoder::DecoderError;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, PlayError, Sink, Source, StreamError};
+
+use crate::game::{Game, GameEvent};
+use crate::map::Map;
+use crate::player::Player;
+use crate::raycaster::walls_between;
+
+const WALL_ATTENUATION: f32 = 0.5; // Volumen que deja pasar cada pared
+const WALL_MUFFLE: f32 = 0.4; // Cuánto apaga los agudos cada pared (0 nada, 1 todo)
+const MIN_FILTER: f32 = 0.08; // Coeficiente del filtro paso bajo con el sonido del todo apagado
+const GAIN_SMOOTHING: f32 = 0.002; // Fracción del cambio de volumen que se aplica por muestra
+
+pub const KEY_SOUND: &str = "src/key_chime.wav";
+pub const GOAL_SOUND: &str = "src/goal_hum.wav";
+pub const ENEMY_SOUND: &str = "src/pasos.wav";
+pub const DOOR_SOUND: &str = "src/door.wav";
+pub const FOOTSTEP_SOUND: &str = "src/pasos.wav";
+const FOOTSTEP_VOLUME: f32 = 0.5;
+
+const KEY_RANGE: f64 = 12.0; // Distancia a la que deja de oírse, en celdas
+const GOAL_RANGE: f64 = 8.0;
+const ENEMY_RANGE: f64 = 10.0;
+const DOOR_RANGE: f64 = 10.0;
+
+#[derive(Debug)]
+pub enum AudioError {
+    Io(std::io::Error),
+    Decode(DecoderError),
+    Stream(StreamError), // No hay dispositivo de salida
+    Play(PlayError),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioError::Io(err) => write!(f, "{}", err),
+            AudioError::Decode(err) => write!(f, "failed to decode: {}", err),
+            AudioError::Stream(err) => write!(f, "no audio output: {}", err),
+            AudioError::Play(err) => write!(f, "failed to play: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+impl From<std::io::Error> for AudioError {
+    fn from(err: std::io::Error) -> Self {
+        AudioError::Io(err)
+    }
+}
+
+impl From<DecoderError> for AudioError {
+    fn from(err: DecoderError) -> Self {
+        AudioError::Decode(err)
+    }
+}
+
+impl From<StreamError> for AudioError {
+    fn from(err: StreamError) -> Self {
+        AudioError::Stream(err)
+    }
+}
+
+impl From<PlayError> for AudioError {
+    fn from(err: PlayError) -> Self {
+        AudioError::Play(err)
+    }
+}
+
+// Sonido ya decodificado en memoria. Clonarlo es barato: todas las copias comparten las muestras.
+#[derive(Clone)]
+pub struct Sound {
+    samples: Arc<[f32]>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Sound {
+    pub fn load(path: &str) -> Result<Sound, AudioError> {
+        let decoder = Decoder::new(BufReader::new(File::open(path)?))?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        let samples = decoder.convert_samples().collect();
+        Ok(Sound { samples, channels, sample_rate })
+    }
+
+    pub fn duration(&self) -> Duration {
+        let frames = self.samples.len() / self.channels.max(1) as usize;
+        Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64)
+    }
+
+    // Fuente que reproduce el sonido una vez o en bucle
+    pub fn source(&self, looping: bool) -> SoundSource {
+        SoundSource { sound: self.clone(), position: 0, looping }
+    }
+}
+
+pub struct SoundSource {
+    sound: Sound,
+    position: usize,
+    looping: bool,
+}
+
+impl Iterator for SoundSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let samples = &self.sound.samples;
+        if self.position >= samples.len() {
+            if !self.looping || samples.is_empty() {
+                return None;
+            }
+            self.position = 0;
+        }
+        self.position += 1;
+        Some(samples[self.position - 1])
+    }
+}
+
+impl Source for SoundSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.sound.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sound.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.looping {
+            None
+        } else {
+            Some(self.sound.duration())
+        }
+    }
+}
+
+// Salida de audio del juego. Si no hay dispositivo se queda en silencio (el backend nulo): los
+// canales y emisores se crean igual pero no suenan, así que el resto del juego no lo nota.
+// Cada sonido se decodifica una sola vez y se guarda para las siguientes veces.
+pub struct Audio {
+    output: Option<(OutputStream, OutputStreamHandle)>,
+    sounds: HashMap<String, Option<Sound>>, // None si no se pudo cargar, para avisar solo una vez
+}
+
+impl Audio {
+    // Abre el dispositivo de salida por defecto; si no hay, avisa y sigue sin sonido
+    pub fn new() -> Self {
+        match OutputStream::try_default() {
+            Ok(output) => Self { output: Some(output), sounds: HashMap::new() },
+            Err(err) => {
+                eprintln!("Playing without sound: {}", AudioError::from(err));
+                Self::silent()
+            }
+        }
+    }
+
+    // Backend nulo: no abre ningún dispositivo ni carga ningún sonido
+    pub fn silent() -> Self {
+        Self { output: None, sounds: HashMap::new() }
+    }
+
+    pub fn is_silent(&self) -> bool {
+        self.output.is_none()
+    }
+
+    // Devuelve el sonido de la caché, cargándolo la primera vez. Los que no se pueden cargar
+    // se avisan una vez y se quedan en None; sin dispositivo no se carga nada.
+    pub fn sound(&mut self, path: &str) -> Option<Sound> {
+        if self.is_silent() {
+            return None;
+        }
+        self.sounds
+            .entry(path.to_string())
+            .or_insert_with(|| match Sound::load(path) {
+                Ok(sound) => Some(sound),
+                Err(err) => {
+                    eprintln!("Failed to load sound {}: {}", path, err);
+                    None
+                }
+            })
+            .clone()
+    }
+
+    fn sink(&self) -> Option<Sink> {
+        let (_, handle) = self.output.as_ref()?;
+        match Sink::try_new(handle) {
+            Ok(sink) => Some(sink),
+            Err(err) => {
+                eprintln!("Failed to play sound: {}", AudioError::from(err));
+                None
+            }
+        }
+    }
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Cola de sonidos que se oyen igual desde cualquier sitio, como la música o los pasos del
+// jugador. Con el backend nulo no tiene sink y todo lo que se le pide no hace nada.
+pub struct Channel {
+    sink: Option<Sink>,
+}
+
+impl Channel {
+    pub fn new(audio: &Audio) -> Self {
+        Self { sink: audio.sink() }
+    }
+
+    // Añade un sonido a la cola; `None` es un sonido que no se pudo cargar y no suena
+    pub fn play(&self, sound: Option<&Sound>, looping: bool) {
+        if let Some(sound) = sound {
+            self.append(sound.source(looping));
+        }
+    }
+
+    fn append<S: Source<Item = f32> + Send + 'static>(&self, source: S) {
+        if let Some(sink) = &self.sink {
+            sink.append(source);
+        }
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        if let Some(sink) = &self.sink {
+            sink.set_volume(volume);
+        }
+    }
+
+    pub fn pause(&self) {
+        if let Some(sink) = &self.sink {
+            sink.pause();
+        }
+    }
+
+    pub fn resume(&self) {
+        if let Some(sink) = &self.sink {
+            sink.play();
+        }
+    }
+
+    // Si ya no le queda nada que reproducir
+    pub fn is_finished(&self) -> bool {
+        self.sink.as_ref().is_none_or(Sink::empty)
+    }
+}
+
+// Cómo se oye un sonido desde la posición del jugador
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mix {
+    pub left: f32,
+    pub right: f32,
+    pub muffle: f32, // 0 se oye limpio, 1 del todo apagado por las paredes
+}
+
+// Volumen de cada oído según la distancia, el ángulo respecto a la vista del jugador y las
+// paredes que hay entre medias. `range` es la distancia a la que deja de oírse.
+pub fn spatialize(map: &Map, listener: &Player, x: f64, y: f64, range: f64) -> Mix {
+    let (dx, dy) = (x - listener.x, y - listener.y);
+    let distance = dx.hypot(dy);
+    if distance >= range {
+        return Mix { left: 0.0, right: 0.0, muffle: 0.0 };
+    }
+    let attenuation = (1.0 - distance / range).powi(2) as f32;
+
+    // Proyección sobre el vector que apunta a la derecha de la vista: -1 izquierda, 1 derecha
+    let pan = if distance > 1e-6 {
+        (dx * -listener.direction.sin() + dy * listener.direction.cos()) / distance
+    } else {
+        0.0
+    };
+    // Panorama de igual potencia, para que el volumen total no baje en el centro
+    let angle = (pan + 1.0) * FRAC_PI_4;
+
+    let walls = walls_between(map, (listener.x, listener.y), (x, y)) as i32;
+    let gain = attenuation * WALL_ATTENUATION.powi(walls);
+
+    Mix {
+        left: gain * angle.cos() as f32,
+        right: gain * angle.sin() as f32,
+        muffle: 1.0 - (1.0 - WALL_MUFFLE).powi(walls),
+    }
+}
+
+// Valores de la mezcla compartidos entre el juego y el hilo de audio
+struct SharedMix {
+    left: AtomicU32,
+    right: AtomicU32,
+    muffle: AtomicU32,
+}
+
+impl SharedMix {
+    fn new() -> Self {
+        let silent = 0.0_f32.to_bits();
+        Self { left: AtomicU32::new(silent), right: AtomicU32::new(silent), muffle: AtomicU32::new(silent) }
+    }
+
+    fn store(&self, mix: Mix) {
+        self.left.store(mix.left.to_bits(), Ordering::Relaxed);
+        self.right.store(mix.right.to_bits(), Ordering::Relaxed);
+        self.muffle.store(mix.muffle.to_bits(), Ordering::Relaxed);
+    }
+
+    fn load(&self) -> Mix {
+        Mix {
+            left: f32::from_bits(self.left.load(Ordering::Relaxed)),
+            right: f32::from_bits(self.right.load(Ordering::Relaxed)),
+            muffle: f32::from_bits(self.muffle.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+// Convierte cualquier sonido a estéreo con el volumen de cada oído de la mezcla compartida,
+// pasando por un filtro paso bajo que apaga los agudos detrás de las paredes
+pub struct Spatial<S> {
+    input: S,
+    mix: Arc<SharedMix>,
+    left: f32,
+    right: f32,
+    filtered: f32,
+    pending_right: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> Iterator for Spatial<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(right) = self.pending_right.take() {
+            return Some(right);
+        }
+
+        // Se mezclan a mono todos los canales de un fotograma de la entrada
+        let channels = self.input.channels().max(1);
+        let mut sum = self.input.next()?;
+        for _ in 1..channels {
+            sum += self.input.next().unwrap_or(0.0);
+        }
+        let sample = sum / channels as f32;
+
+        // Los cambios de volumen se suavizan para que no se oigan chasquidos
+        let target = self.mix.load();
+        self.left += (target.left - self.left) * GAIN_SMOOTHING;
+        self.right += (target.right - self.right) * GAIN_SMOOTHING;
+        let alpha = 1.0 - target.muffle * (1.0 - MIN_FILTER);
+        self.filtered += (sample - self.filtered) * alpha;
+
+        self.pending_right = Some(self.filtered * self.right);
+        Some(self.filtered * self.left)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Spatial<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        let channels = self.input.channels().max(1) as usize;
+        self.input.current_frame_len().map(|len| len / channels * 2)
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+// Fuente de sonido colocada en el mundo. Puede sonar en bucle o una sola vez; cada
+// fotograma hay que llamar a `update` para recalcular cómo se oye desde el jugador.
+pub struct Emitter {
+    pub x: f64,
+    pub y: f64,
+    pub range: f64,
+    channel: Channel,
+    mix: Arc<SharedMix>,
+}
+
+impl Emitter {
+    pub fn new(audio: &Audio, x: f64, y: f64, range: f64) -> Self {
+        Self { x, y, range, channel: Channel::new(audio), mix: Arc::new(SharedMix::new()) }
+    }
+
+    // Añade un sonido a la cola del emisor; `None` es un sonido que no se pudo cargar
+    pub fn play(&self, sound: Option<&Sound>, looping: bool) {
+        let Some(sound) = sound else {
+            return;
+        };
+        let mix = self.mix.load();
+        self.channel.append(Spatial {
+            input: sound.source(looping),
+            mix: Arc::clone(&self.mix),
+            left: mix.left,
+            right: mix.right,
+            filtered: 0.0,
+            pending_right: None,
+        });
+    }
+
+    pub fn update(&self, map: &Map, listener: &Player) {
+        self.mix.store(spatialize(map, listener, self.x, self.y, self.range));
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.channel.set_volume(volume);
+    }
+
+    pub fn pause(&self) {
+        self.channel.pause();
+    }
+
+    pub fn resume(&self) {
+        self.channel.resume();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.channel.is_finished()
+    }
+}
+
+// Sonidos de una partida: la llave y la meta suenan en bucle para que se puedan encontrar
+// de oído, los enemigos llevan sus pasos, las puertas suenan al abrirse o cerrarse y los
+// pasos del jugador suenan mientras camina
+pub struct GameSounds {
+    key: Option<Emitter>,
+    goal: Emitter,
+    enemies: Vec<Emitter>,
+    one_shots: Vec<Emitter>,
+    footsteps: Channel,
+    volume: f32, // Volumen general de los ajustes
+}
+
+impl GameSounds {
+    pub fn new(audio: &mut Audio, game: &Game, volume: f32) -> Self {
+        let mut looping = |path: &str, x: f64, y: f64, range: f64| {
+            let emitter = Emitter::new(audio, x, y, range);
+            emitter.update(&game.map, &game.player);
+            emitter.play(audio.sound(path).as_ref(), true);
+            emitter
+        };
+
+        let key = if game.key.collected { None } else { Some(looping(KEY_SOUND, game.key.x, game.key.y, KEY_RANGE)) };
+        let goal = looping(GOAL_SOUND, game.goal.x, game.goal.y, GOAL_RANGE);
+        let enemies =
+            game.map.enemies.iter().map(|enemy| looping(ENEMY_SOUND, enemy.x, enemy.y, ENEMY_RANGE)).collect();
+
+        let footsteps = Channel::new(audio);
+        let mut sounds = Self { key, goal, enemies, one_shots: Vec::new(), footsteps, volume };
+        sounds.set_volume(volume);
+        sounds
+    }
+
+    // Vuelve a empezar con los objetos y enemigos de otra partida
+    pub fn reset(&mut self, audio: &mut Audio, game: &Game) {
+        *self = GameSounds::new(audio, game, self.volume);
+    }
+
+    // Cambia el volumen de todos los sonidos de la partida, también los que suenen después
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        for emitter in self.emitters() {
+            emitter.set_volume(volume);
+        }
+        self.footsteps.set_volume(FOOTSTEP_VOLUME * volume);
+    }
+
+    // Sigue a los objetos y enemigos de la partida y suena lo que haya pasado en el último tick
+    pub fn update(&mut self, audio: &mut Audio, game: &Game, events: &[GameEvent]) {
+        for event in events {
+            match *event {
+                GameEvent::DoorUsed { x, y } | GameEvent::DoorMoved { x, y } => {
+                    self.play_once(audio, DOOR_SOUND, x, y, DOOR_RANGE)
+                }
+                GameEvent::KeyCollected => {
+                    self.key = None;
+                    self.play_once(audio, KEY_SOUND, game.player.x, game.player.y, KEY_RANGE);
+                }
+                // Los pasos no se encadenan: el siguiente empieza cuando acaba el anterior
+                GameEvent::Footstep => {
+                    if self.footsteps.is_finished() {
+                        self.footsteps.play(audio.sound(FOOTSTEP_SOUND).as_ref(), false);
+                    }
+                }
+            }
+        }
+
+        for (emitter, enemy) in self.enemies.iter_mut().zip(&game.map.enemies) {
+            emitter.x = enemy.x;
+            emitter.y = enemy.y;
+        }
+        self.one_shots.retain(|emitter| !emitter.is_finished());
+
+        for emitter in self.emitters() {
+            emitter.update(&game.map, &game.player);
+            emitter.resume();
+        }
+        self.footsteps.resume();
+    }
+
+    fn play_once(&mut self, audio: &mut Audio, path: &str, x: f64, y: f64, range: f64) {
+        let emitter = Emitter::new(audio, x, y, range);
+        emitter.set_volume(self.volume);
+        emitter.play(audio.sound(path).as_ref(), false);
+        self.one_shots.push(emitter);
+    }
+
+    // Para los menús: los sonidos se quedan donde estaban hasta el siguiente `update`
+    pub fn pause(&self) {
+        for emitter in self.emitters() {
+            emitter.pause();
+        }
+        self.footsteps.pause();
+    }
+
+    fn emitters(&self) -> impl Iterator<Item = &Emitter> {
+        self.key.iter().chain([&self.goal]).chain(&self.enemies).chain(&self.one_shots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGE: f64 = 4.0;
+
+    // Sala vacía de 7x7 con una pared en medio de la fila de abajo
+    fn room() -> Map {
+        "width 7\nheight 7\nplayer 3.5 3.5\ngrid\n1111111\n1000001\n1000001\n1000001\n1000001\n1001001\n1111111\n"
+            .parse()
+            .unwrap()
+    }
+
+    fn listener() -> Player {
+        Player::new(3.5, 3.5, 0.0) // Mirando hacia +x, con la derecha hacia +y
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn sounds_to_the_side_only_reach_one_ear() {
+        let map = room();
+        let gain = (1.0 - 1.0 / RANGE).powi(2) as f32;
+
+        let right = spatialize(&map, &listener(), 3.5, 4.5, RANGE);
+        assert_close(right.left, 0.0);
+        assert_close(right.right, gain);
+
+        let left = spatialize(&map, &listener(), 3.5, 2.5, RANGE);
+        assert_close(left.left, gain);
+        assert_close(left.right, 0.0);
+
+        // Al frente los dos oídos suman la misma potencia que a un lado
+        let ahead = spatialize(&map, &listener(), 4.5, 3.5, RANGE);
+        assert_close(ahead.left, ahead.right);
+        assert_close(ahead.left.powi(2) + ahead.right.powi(2), gain.powi(2));
+    }
+
+    #[test]
+    fn volume_falls_off_with_distance() {
+        let map = room();
+        let near = spatialize(&map, &listener(), 4.5, 3.5, RANGE);
+        let far = spatialize(&map, &listener(), 5.5, 3.5, RANGE);
+        assert_close(near.left + near.right, (1.0 - 1.0 / RANGE).powi(2) as f32 * 2.0_f32.sqrt());
+        assert_close(far.left + far.right, (1.0 - 2.0 / RANGE).powi(2) as f32 * 2.0_f32.sqrt());
+
+        let silent = spatialize(&map, &listener(), 3.5, 3.5 - RANGE, RANGE);
+        assert_eq!((silent.left, silent.right), (0.0, 0.0));
+    }
+
+    #[test]
+    fn walls_in_between_muffle_the_sound() {
+        let map = room();
+        let listener = Player::new(2.5, 5.5, 0.0);
+        let clear = spatialize(&map, &listener, 2.5, 4.5, RANGE);
+        let behind_wall = spatialize(&map, &listener, 4.5, 5.5, RANGE);
+        assert_eq!(clear.muffle, 0.0);
+        assert_close(behind_wall.muffle, WALL_MUFFLE);
+
+        // A la misma distancia, la pared deja pasar solo WALL_ATTENUATION del volumen
+        let open = spatialize(&map, &listener, 2.5, 3.5, RANGE);
+        let level = |mix: Mix| mix.left.hypot(mix.right);
+        assert_close(level(behind_wall), level(open) * WALL_ATTENUATION);
+    }
+}
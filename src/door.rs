@@ -0,0 +1,119 @@
+use std::fmt;
+use std::str::FromStr;
+
+const DOOR_SPEED: f64 = 1.5; // Fracción de la puerta que se desliza por segundo
+const DOOR_OPEN_TIME: f64 = 3.0; // Segundos que la puerta queda abierta antes de cerrarse
+const DOOR_PASSABLE: f64 = 0.9; // A partir de esta apertura se puede atravesar
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DoorState {
+    Closed,
+    Opening,
+    Open,
+    Closing,
+}
+
+impl FromStr for DoorState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "closed" => Ok(DoorState::Closed),
+            "opening" => Ok(DoorState::Opening),
+            "open" => Ok(DoorState::Open),
+            "closing" => Ok(DoorState::Closing),
+            _ => Err(format!("unknown door state `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for DoorState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DoorState::Closed => "closed",
+            DoorState::Opening => "opening",
+            DoorState::Open => "open",
+            DoorState::Closing => "closing",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Puerta corredera que ocupa una celda del mapa
+#[derive(Clone, Copy, Debug)]
+pub struct Door {
+    pub open_amount: f64, // 0.0 cerrada, 1.0 abierta del todo
+    pub state: DoorState,
+    pub timer: f64, // Tiempo que lleva abierta
+}
+
+impl Default for Door {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Door {
+    pub fn new() -> Self {
+        Self {
+            open_amount: 0.0,
+            state: DoorState::Closed,
+            timer: 0.0,
+        }
+    }
+
+    // Abre la puerta si está cerrada o cerrándose; si ya está abierta reinicia la espera.
+    // Devuelve true si ha empezado a abrirse.
+    pub fn activate(&mut self) -> bool {
+        match self.state {
+            DoorState::Closed | DoorState::Closing => {
+                self.state = DoorState::Opening;
+                true
+            }
+            DoorState::Open => {
+                self.timer = 0.0;
+                false
+            }
+            DoorState::Opening => false,
+        }
+    }
+
+    // Avanza la animación; una puerta ocupada no se cierra y vuelve a abrirse si se estaba cerrando.
+    // Devuelve true si la puerta ha empezado a moverse sola, para que suene.
+    pub fn update(&mut self, dt: f64, occupied: bool) -> bool {
+        let previous = self.state;
+        if occupied && self.state == DoorState::Closing {
+            self.state = DoorState::Opening;
+        }
+
+        match self.state {
+            DoorState::Opening => {
+                self.open_amount += DOOR_SPEED * dt;
+                if self.open_amount >= 1.0 {
+                    self.open_amount = 1.0;
+                    self.state = DoorState::Open;
+                    self.timer = 0.0;
+                }
+            }
+            DoorState::Open => {
+                self.timer += dt;
+                if self.timer >= DOOR_OPEN_TIME && !occupied {
+                    self.state = DoorState::Closing;
+                }
+            }
+            DoorState::Closing => {
+                self.open_amount -= DOOR_SPEED * dt;
+                if self.open_amount <= 0.0 {
+                    self.open_amount = 0.0;
+                    self.state = DoorState::Closed;
+                }
+            }
+            DoorState::Closed => {}
+        }
+        self.state != previous && matches!(self.state, DoorState::Opening | DoorState::Closing)
+    }
+
+    pub fn is_passable(&self) -> bool {
+        self.open_amount >= DOOR_PASSABLE
+    }
+}
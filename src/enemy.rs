@@ -0,0 +1,206 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::map::Map;
+use crate::pathfinding::{find_path, is_walkable};
+use crate::raycaster::has_line_of_sight;
+
+const PATROL_SPEED: f64 = 1.5; // Unidades del mapa por segundo
+const CHASE_SPEED: f64 = 2.4; // Algo más lento que el jugador para que se pueda escapar
+const SIGHT_RANGE: f64 = 8.0; // Distancia máxima a la que ve al jugador
+const LOSE_SIGHT_TIME: f64 = 3.0; // Segundos sin ver al jugador antes de volver a patrullar
+const REPATH_TIME: f64 = 0.5; // Segundos entre recálculos del camino
+const CATCH_DISTANCE: f64 = 0.5; // Distancia a la que atrapa al jugador
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyState {
+    Patrolling,
+    Chasing,
+}
+
+impl FromStr for EnemyState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "patrolling" => Ok(EnemyState::Patrolling),
+            "chasing" => Ok(EnemyState::Chasing),
+            _ => Err(format!("unknown enemy state `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for EnemyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EnemyState::Patrolling => "patrolling",
+            EnemyState::Chasing => "chasing",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Enemigo que recorre sus puntos de patrulla y persigue al jugador cuando lo ve
+pub struct Enemy {
+    pub x: f64,
+    pub y: f64,
+    pub state: EnemyState,
+    pub waypoints: Vec<(f64, f64)>, // Puntos de patrulla, recorridos en ciclo
+    next_waypoint: usize,
+    path: Vec<(usize, usize)>, // Celdas que faltan hasta el objetivo actual
+    repath_timer: f64,
+    unseen_timer: f64, // Tiempo que lleva sin ver al jugador mientras lo persigue
+}
+
+impl Enemy {
+    pub fn new(x: f64, y: f64, waypoints: Vec<(f64, f64)>) -> Self {
+        Self {
+            x,
+            y,
+            state: EnemyState::Patrolling,
+            waypoints,
+            next_waypoint: 0,
+            path: Vec::new(),
+            repath_timer: 0.0,
+            unseen_timer: 0.0,
+        }
+    }
+
+    pub fn can_see(&self, map: &Map, x: f64, y: f64) -> bool {
+        let distance = (x - self.x).hypot(y - self.y);
+        distance <= SIGHT_RANGE && has_line_of_sight(map, (self.x, self.y), (x, y))
+    }
+
+    pub fn has_caught(&self, x: f64, y: f64) -> bool {
+        (x - self.x).hypot(y - self.y) < CATCH_DISTANCE
+    }
+
+    pub fn update(&mut self, dt: f64, map: &Map, player_x: f64, player_y: f64) {
+        let sees_player = self.can_see(map, player_x, player_y);
+        match self.state {
+            EnemyState::Patrolling if sees_player => self.set_state(EnemyState::Chasing),
+            EnemyState::Chasing if sees_player => self.unseen_timer = 0.0,
+            EnemyState::Chasing => {
+                self.unseen_timer += dt;
+                if self.unseen_timer >= LOSE_SIGHT_TIME {
+                    self.set_state(EnemyState::Patrolling);
+                }
+            }
+            EnemyState::Patrolling => {}
+        }
+
+        let (target, speed) = match self.state {
+            EnemyState::Chasing => ((player_x, player_y), CHASE_SPEED),
+            EnemyState::Patrolling => match self.waypoints.get(self.next_waypoint) {
+                Some(&waypoint) => (waypoint, PATROL_SPEED),
+                None => return,
+            },
+        };
+
+        self.repath_timer -= dt;
+        if self.repath_timer <= 0.0 {
+            self.repath(map, target);
+        }
+
+        let arrived = self.walk(speed * dt, target, map);
+        if arrived && self.state == EnemyState::Patrolling {
+            self.next_waypoint = (self.next_waypoint + 1) % self.waypoints.len();
+            self.repath_timer = 0.0;
+        }
+    }
+
+    // Punto de patrulla hacia el que va
+    pub fn next_waypoint(&self) -> usize {
+        self.next_waypoint
+    }
+
+    // Coloca al enemigo en un estado guardado; el camino se recalcula en el siguiente `update`
+    pub fn restore(&mut self, x: f64, y: f64, state: EnemyState, next_waypoint: usize) {
+        self.x = x;
+        self.y = y;
+        self.next_waypoint = if self.waypoints.is_empty() { 0 } else { next_waypoint % self.waypoints.len() };
+        self.set_state(state);
+    }
+
+    fn set_state(&mut self, state: EnemyState) {
+        self.state = state;
+        self.unseen_timer = 0.0;
+        self.repath_timer = 0.0;
+        self.path.clear();
+    }
+
+    fn repath(&mut self, map: &Map, target: (f64, f64)) {
+        self.path = find_path(map, cell_of(self.x, self.y), cell_of(target.0, target.1)).unwrap_or_default();
+        self.repath_timer = REPATH_TIME;
+    }
+
+    // Avanza por el centro de las celdas del camino y, ya en la celda del objetivo,
+    // directamente hacia él. Devuelve true al llegar al objetivo.
+    fn walk(&mut self, mut step: f64, target: (f64, f64), map: &Map) -> bool {
+        while step > 0.0 {
+            // Si desde el último cálculo se ha cerrado una puerta en el camino se busca otro
+            if self.path.first().is_some_and(|&(x, y)| !is_walkable(map, x, y)) {
+                self.repath(map, target);
+            }
+            let (next_x, next_y) = match self.path.first() {
+                Some(&(x, y)) => (x as f64 + 0.5, y as f64 + 0.5),
+                None if cell_of(self.x, self.y) == cell_of(target.0, target.1) => target,
+                None => return false,
+            };
+
+            let distance = (next_x - self.x).hypot(next_y - self.y);
+            if distance > step {
+                self.x += (next_x - self.x) / distance * step;
+                self.y += (next_y - self.y) / distance * step;
+                return false;
+            }
+
+            self.x = next_x;
+            self.y = next_y;
+            step -= distance;
+            if self.path.is_empty() {
+                return true;
+            }
+            self.path.remove(0);
+        }
+        false
+    }
+}
+
+fn cell_of(x: f64, y: f64) -> (usize, usize) {
+    (x.max(0.0) as usize, y.max(0.0) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::door::DoorState;
+
+    // Un pasillo cortado por una puerta en (4, 1), con un rodeo por abajo
+    const MAP: &str = "width 9\nheight 5\nplayer 1.5 1.5\ngrid\n111111111\n1000D0001\n101111101\n100000001\n111111111\n";
+
+    #[test]
+    fn enemy_does_not_walk_through_a_door_that_closed() {
+        let mut map: Map = MAP.parse().unwrap();
+        let door = map.doors.get_mut(&(4, 1)).unwrap();
+        door.open_amount = 1.0;
+        door.state = DoorState::Open;
+
+        // El jugador queda fuera de su vista para que solo patrulle
+        let mut enemy = Enemy::new(3.5, 1.5, vec![(7.5, 1.5)]);
+        enemy.update(0.01, &map, 100.0, 100.0);
+        assert!(enemy.path.contains(&(4, 1)));
+
+        let door = map.doors.get_mut(&(4, 1)).unwrap();
+        door.open_amount = 0.0;
+        door.state = DoorState::Closed;
+        for _ in 0..200 {
+            enemy.update(0.05, &map, 100.0, 100.0);
+            assert_ne!(cell_of(enemy.x, enemy.y), (4, 1), "the enemy walked into the closed door");
+            if cell_of(enemy.x, enemy.y) == (7, 1) {
+                return;
+            }
+        }
+        panic!("the enemy did not go around the door, it is at {} {}", enemy.x, enemy.y);
+    }
+}
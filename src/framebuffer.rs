@@ -1,3 +1,7 @@
+use std::path::Path;
+
+use image::{ImageFormat, ImageResult, Rgb, RgbImage};
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
@@ -26,4 +30,13 @@ impl Framebuffer {
             false
         }
     }
+
+    // Guarda el contenido del framebuffer como imagen PNG
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        let image = RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let color = self.buffer[y as usize * self.width + x as usize];
+            Rgb([(color >> 16) as u8, (color >> 8) as u8, color as u8])
+        });
+        image.save_with_format(path, ImageFormat::Png)
+    }
 }
@@ -0,0 +1,201 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::input::Action;
+use crate::item::Item;
+use crate::map::Map;
+use crate::placement::{place_key_and_goal, PlacementError};
+use crate::player::Player;
+use crate::replay::Tick;
+
+const MIN_ITEM_DISTANCE: usize = 8; // Pasos mínimos entre el inicio, la llave y la meta
+const PICKUP_DISTANCE: f64 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Playing,
+    Won,
+    Lost,
+}
+
+impl FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "playing" => Ok(Outcome::Playing),
+            "won" => Ok(Outcome::Won),
+            "lost" => Ok(Outcome::Lost),
+            _ => Err(format!("unknown outcome `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Outcome::Playing => "playing",
+            Outcome::Won => "won",
+            Outcome::Lost => "lost",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Cosas que han pasado durante un tick, para que el audio o la interfaz reaccionen
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameEvent {
+    DoorUsed { x: f64, y: f64 },  // El jugador ha empezado a abrirla; centro de su celda
+    DoorMoved { x: f64, y: f64 }, // La puerta ha empezado a cerrarse o a reabrirse sola
+    KeyCollected,
+    Footstep, // El jugador se ha movido
+}
+
+// Estado de una partida. Todo lo que cambia la partida pasa por `step`, así que la misma
+// semilla y los mismos ticks dan siempre el mismo resultado, bit a bit.
+pub struct Game {
+    pub map: Map,
+    pub player: Player,
+    pub key: Item,
+    pub goal: Item,
+    pub outcome: Outcome,
+    pub elapsed: f64, // Segundos de juego
+    pub seed: u64,
+    pub events: Vec<GameEvent>, // Lo que ha pasado en el último `step`
+}
+
+impl Game {
+    // La semilla decide dónde aparecen la llave y la meta, con el mismo generador que los laberintos
+    pub fn new(map: Map, seed: u64) -> Result<Self, PlacementError> {
+        let player = Player::new(map.spawn_x, map.spawn_y, map.spawn_direction);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let placement = place_key_and_goal(&map, (player.x, player.y), MIN_ITEM_DISTANCE, &mut rng)?;
+        let key = Item { x: placement.key.0, y: placement.key.1, collected: false };
+        let goal = Item { x: placement.goal.0, y: placement.goal.1, collected: false };
+        Ok(Self::with_items(map, player, key, goal, seed))
+    }
+
+    // Partida con la llave y la meta ya colocadas, por ejemplo al cargar una partida guardada
+    pub fn with_items(map: Map, player: Player, key: Item, goal: Item, seed: u64) -> Self {
+        Self {
+            map,
+            player,
+            key,
+            goal,
+            outcome: Outcome::Playing,
+            elapsed: 0.0,
+            seed,
+            events: Vec::new(),
+        }
+    }
+
+    // Avanza la partida un tick con las acciones y el tiempo indicados
+    pub fn step(&mut self, tick: &Tick) {
+        self.events.clear();
+        if self.outcome != Outcome::Playing {
+            return;
+        }
+        self.elapsed += tick.dt;
+        let player = &mut self.player;
+        let map = &mut self.map;
+
+        let move_distance = player.move_speed * tick.dt;
+        let turn_angle = player.turn_speed * tick.dt;
+
+        let mut moved = false;
+        if tick.is_down(Action::MoveForward) {
+            moved |= player.move_forward(move_distance, map);
+        }
+        if tick.is_down(Action::MoveBackward) {
+            moved |= player.move_backward(move_distance, map);
+        }
+        if tick.is_down(Action::StrafeLeft) {
+            moved |= player.strafe_left(move_distance, map);
+        }
+        if tick.is_down(Action::StrafeRight) {
+            moved |= player.strafe_right(move_distance, map);
+        }
+        if moved {
+            self.events.push(GameEvent::Footstep);
+        }
+        if tick.is_down(Action::TurnLeft) {
+            player.turn_left(turn_angle);
+        }
+        if tick.is_down(Action::TurnRight) {
+            player.turn_right(turn_angle);
+        }
+
+        if tick.was_pressed(Action::Use) && player.use_door(map) {
+            let (x, y) = player.use_target();
+            self.events.push(GameEvent::DoorUsed { x: x.floor() + 0.5, y: y.floor() + 0.5 });
+        }
+        for (x, y) in map.update_doors(tick.dt, player.x, player.y, player.radius) {
+            self.events.push(GameEvent::DoorMoved { x: x as f64 + 0.5, y: y as f64 + 0.5 });
+        }
+
+        // Si lo atrapan ya no puede ganar en el mismo tick
+        if map.update_enemies(tick.dt, player.x, player.y) {
+            self.outcome = Outcome::Lost;
+            return;
+        }
+
+        player.turn_right(tick.mouse_turn);
+
+        let touches = |item: &Item| (player.x - item.x).abs() < PICKUP_DISTANCE && (player.y - item.y).abs() < PICKUP_DISTANCE;
+        if touches(&self.key) && !self.key.collected {
+            self.key.collected = true;
+            self.events.push(GameEvent::KeyCollected);
+        }
+        if self.key.collected && touches(&self.goal) {
+            self.outcome = Outcome::Won;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::MapSource;
+
+    // Si cambia el generador o la colocación, las grabaciones y partidas guardadas dejan de
+    // valer y hay que subir sus versiones
+    #[test]
+    fn seed_gives_known_item_cells() {
+        let map = MapSource::File("src/maps/level1.map".to_string()).load().unwrap();
+        let game = Game::new(map, 42).unwrap();
+        assert_eq!((game.key.x, game.key.y), (7.5, 15.5));
+        assert_eq!((game.goal.x, game.goal.y), (17.5, 16.5));
+    }
+
+    #[test]
+    fn caught_player_does_not_win_on_the_goal() {
+        let map: Map = "width 5\nheight 3\nplayer 1.5 1.5\nenemy 1.8 1.5\ngrid\n11111\n10001\n11111\n".parse().unwrap();
+        let player = Player::new(map.spawn_x, map.spawn_y, map.spawn_direction);
+        let key = Item { x: 3.5, y: 1.5, collected: true };
+        let goal = Item { x: 1.5, y: 1.5, collected: false };
+        let mut game = Game::with_items(map, player, key, goal, 1);
+        game.step(&Tick { dt: 0.01, down: 0, pressed: 0, mouse_turn: 0.0 });
+        assert_eq!(game.outcome, Outcome::Lost);
+    }
+
+    #[test]
+    fn using_an_open_door_does_not_sound_it_again() {
+        let map: Map = "width 5\nheight 3\nplayer 1.5 1.5\ngrid\n11111\n10D01\n11111\n".parse().unwrap();
+        let player = Player::new(map.spawn_x, map.spawn_y, map.spawn_direction);
+        let item = Item { x: 3.5, y: 1.5, collected: false };
+        let mut game = Game::with_items(map, player, item, item, 1);
+        let door_used = |game: &Game| game.events.iter().any(|event| matches!(event, GameEvent::DoorUsed { .. }));
+
+        let use_door = Tick { dt: 0.1, down: 0, pressed: Action::Use.mask(), mouse_turn: 0.0 };
+        game.step(&use_door);
+        assert!(door_used(&game));
+        for _ in 0..10 {
+            game.step(&Tick { pressed: 0, ..use_door });
+        }
+        game.step(&use_door);
+        assert!(!door_used(&game));
+    }
+}
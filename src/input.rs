@@ -0,0 +1,364 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use minifb::{Key, MouseButton, Window};
+
+// Acciones del juego; el código del juego pregunta por ellas en vez de por teclas concretas
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    TurnLeft,
+    TurnRight,
+    Use,
+    VolumeUp,
+    VolumeDown,
+    Pause,
+    Confirm,
+    Quit,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+}
+
+impl Action {
+    pub const ALL: [Action; 16] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Use,
+        Action::VolumeUp,
+        Action::VolumeDown,
+        Action::Pause,
+        Action::Confirm,
+        Action::Quit,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuLeft,
+        Action::MenuRight,
+    ];
+
+    // Nombre de la acción en los archivos de controles
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBackward => "move_backward",
+            Action::StrafeLeft => "strafe_left",
+            Action::StrafeRight => "strafe_right",
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::Use => "use",
+            Action::VolumeUp => "volume_up",
+            Action::VolumeDown => "volume_down",
+            Action::Pause => "pause",
+            Action::Confirm => "confirm",
+            Action::Quit => "quit",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::MenuLeft => "menu_left",
+            Action::MenuRight => "menu_right",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    // Bit de la acción en las máscaras de `Input::down_mask` y de las repeticiones
+    pub fn mask(self) -> u32 {
+        1 << self.index()
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| format!("unknown action `{}`", s))
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Tecla o botón del ratón asignado a una acción
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    // Las teclas se escriben con el nombre de `minifb::Key` (`W`, `Up`, `Space`, `Key1`...)
+    // y los botones como `MouseLeft`, `MouseMiddle` o `MouseRight`; sin distinguir mayúsculas
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mouse = [
+            ("MouseLeft", MouseButton::Left),
+            ("MouseMiddle", MouseButton::Middle),
+            ("MouseRight", MouseButton::Right),
+        ];
+        if let Some(&(_, button)) = mouse.iter().find(|(name, _)| name.eq_ignore_ascii_case(s)) {
+            return Ok(Binding::Mouse(button));
+        }
+        KEYS.iter()
+            .find(|key| format!("{:?}", key).eq_ignore_ascii_case(s))
+            .map(|&key| Binding::Key(key))
+            .ok_or_else(|| format!("unknown key `{}`", s))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Mouse(button) => write!(f, "Mouse{:?}", button),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    Io(std::io::Error),
+    Parse { line: usize, column: usize, message: String },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Io(err) => write!(f, "{}", err),
+            InputError::Parse { line, column, message } => {
+                write!(f, "line {}, column {}: {}", line, column, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+impl From<std::io::Error> for InputError {
+    fn from(err: std::io::Error) -> Self {
+        InputError::Io(err)
+    }
+}
+
+// Teclas y botones asignados a cada acción
+#[derive(Clone, Debug)]
+pub struct InputMap {
+    bindings: Vec<Vec<Binding>>, // Indexado por acción
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let mut map = Self { bindings: vec![Vec::new(); Action::ALL.len()] };
+        map.bind(Action::MoveForward, &[Binding::Key(Key::W), Binding::Key(Key::Up)]);
+        map.bind(Action::MoveBackward, &[Binding::Key(Key::S), Binding::Key(Key::Down)]);
+        map.bind(Action::StrafeLeft, &[Binding::Key(Key::A)]);
+        map.bind(Action::StrafeRight, &[Binding::Key(Key::D)]);
+        map.bind(Action::TurnLeft, &[Binding::Key(Key::Left)]);
+        map.bind(Action::TurnRight, &[Binding::Key(Key::Right)]);
+        map.bind(Action::Use, &[Binding::Key(Key::Space), Binding::Mouse(MouseButton::Right)]);
+        map.bind(Action::VolumeUp, &[Binding::Key(Key::Equal)]);
+        map.bind(Action::VolumeDown, &[Binding::Key(Key::Minus)]);
+        map.bind(Action::Pause, &[Binding::Key(Key::P), Binding::Key(Key::Escape)]);
+        map.bind(Action::Confirm, &[Binding::Key(Key::Enter)]);
+        map.bind(Action::MenuUp, &[Binding::Key(Key::Up), Binding::Key(Key::W)]);
+        map.bind(Action::MenuDown, &[Binding::Key(Key::Down), Binding::Key(Key::S)]);
+        map.bind(Action::MenuLeft, &[Binding::Key(Key::Left), Binding::Key(Key::A)]);
+        map.bind(Action::MenuRight, &[Binding::Key(Key::Right), Binding::Key(Key::D)]);
+        map
+    }
+}
+
+impl InputMap {
+    // Empieza por los controles por defecto y aplica encima los del archivo
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<InputMap, InputError> {
+        let contents = fs::read_to_string(path)?;
+        contents.parse()
+    }
+
+    pub fn bind(&mut self, action: Action, bindings: &[Binding]) {
+        self.bindings[action.index()] = bindings.to_vec();
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        &self.bindings[action.index()]
+    }
+}
+
+// Formato de los archivos de controles:
+//
+//   # comentario
+//   move_forward W Up
+//   use Space MouseRight
+//   pause
+//
+// Cada línea es una acción seguida de todas sus teclas; sustituye a las teclas por
+// defecto de esa acción. Una acción sin teclas queda desactivada y las acciones que
+// no aparecen conservan sus teclas por defecto.
+impl FromStr for InputMap {
+    type Err = InputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = InputMap::default();
+
+        for (i, line) in s.lines().enumerate() {
+            let line_no = i + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace().map(|word| {
+                let column = word.as_ptr() as usize - line.as_ptr() as usize + 1;
+                (word, column)
+            });
+            let Some((name, column)) = words.next() else {
+                continue;
+            };
+            let action: Action = name
+                .parse()
+                .map_err(|message| InputError::Parse { line: line_no, column, message })?;
+
+            let bindings = words
+                .map(|(word, column)| {
+                    word.parse::<Binding>()
+                        .map_err(|message| InputError::Parse { line: line_no, column, message })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            map.bind(action, &bindings);
+        }
+
+        Ok(map)
+    }
+}
+
+// Estado de las acciones en el fotograma actual
+pub struct Input {
+    pub map: InputMap,
+    down: [bool; Action::ALL.len()],
+    previous: [bool; Action::ALL.len()],
+}
+
+impl Input {
+    pub fn new(map: InputMap) -> Self {
+        Self {
+            map,
+            down: [false; Action::ALL.len()],
+            previous: [false; Action::ALL.len()],
+        }
+    }
+
+    // Lee el teclado y el ratón de la ventana; se llama una vez por fotograma
+    pub fn update(&mut self, window: &Window) {
+        self.previous = self.down;
+        for action in Action::ALL {
+            self.down[action.index()] = self.map.bindings(action).iter().any(|binding| match *binding {
+                Binding::Key(key) => window.is_key_down(key),
+                Binding::Mouse(button) => window.get_mouse_down(button),
+            });
+        }
+    }
+
+    pub fn is_down(&self, action: Action) -> bool {
+        self.down[action.index()]
+    }
+
+    // Si la acción se ha empezado a pulsar en este fotograma
+    pub fn was_pressed(&self, action: Action) -> bool {
+        self.down[action.index()] && !self.previous[action.index()]
+    }
+
+    // Acciones pulsadas como máscara de bits, para grabarlas
+    pub fn down_mask(&self) -> u32 {
+        Action::ALL.into_iter().filter(|&action| self.is_down(action)).map(Action::mask).sum()
+    }
+
+    pub fn pressed_mask(&self) -> u32 {
+        Action::ALL.into_iter().filter(|&action| self.was_pressed(action)).map(Action::mask).sum()
+    }
+}
+
+// Teclas que se pueden usar en los archivos de controles
+const KEYS: [Key; 106] = [
+    Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4,
+    Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I,
+    Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R,
+    Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8,
+    Key::F9, Key::F10, Key::F11, Key::F12, Key::F13, Key::F14, Key::F15,
+    Key::Down, Key::Left, Key::Right, Key::Up,
+    Key::Apostrophe, Key::Backquote, Key::Backslash, Key::Comma, Key::Equal,
+    Key::LeftBracket, Key::Minus, Key::Period, Key::RightBracket, Key::Semicolon, Key::Slash,
+    Key::Backspace, Key::Delete, Key::End, Key::Enter, Key::Escape,
+    Key::Home, Key::Insert, Key::Menu, Key::PageDown, Key::PageUp,
+    Key::Pause, Key::Space, Key::Tab, Key::NumLock, Key::CapsLock, Key::ScrollLock,
+    Key::LeftShift, Key::RightShift, Key::LeftCtrl, Key::RightCtrl,
+    Key::NumPad0, Key::NumPad1, Key::NumPad2, Key::NumPad3, Key::NumPad4,
+    Key::NumPad5, Key::NumPad6, Key::NumPad7, Key::NumPad8, Key::NumPad9,
+    Key::NumPadDot, Key::NumPadSlash, Key::NumPadAsterisk, Key::NumPadMinus,
+    Key::NumPadPlus, Key::NumPadEnter,
+    Key::LeftAlt, Key::RightAlt, Key::LeftSuper, Key::RightSuper,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Línea, columna y mensaje del error al leer `text`
+    fn error_of(text: &str) -> (usize, usize, String) {
+        match text.parse::<InputMap>() {
+            Err(InputError::Parse { line, column, message }) => (line, column, message),
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn unknown_action_is_reported() {
+        assert_eq!(error_of("# controles\n  jump Space\n"), (2, 3, "unknown action `jump`".to_string()));
+    }
+
+    #[test]
+    fn unknown_key_is_reported() {
+        assert_eq!(error_of("use Space Banana\n"), (1, 11, "unknown key `Banana`".to_string()));
+    }
+
+    #[test]
+    fn action_without_keys_is_disabled_and_the_rest_keep_their_defaults() {
+        let map: InputMap = "quit\npause\n".parse().unwrap();
+        assert!(map.bindings(Action::Quit).is_empty());
+        assert!(map.bindings(Action::Pause).is_empty());
+        let defaults = InputMap::default();
+        assert_eq!(map.bindings(Action::MoveForward), defaults.bindings(Action::MoveForward));
+        assert_eq!(map.bindings(Action::Use), defaults.bindings(Action::Use));
+    }
+
+    #[test]
+    fn mouse_buttons_can_be_bound() {
+        let map: InputMap = "use mousemiddle E\n".parse().unwrap();
+        assert_eq!(map.bindings(Action::Use), [Binding::Mouse(MouseButton::Middle), Binding::Key(Key::E)]);
+    }
+
+    #[test]
+    fn shipped_controls_file_parses() {
+        let map = InputMap::load_from_path("src/controls.cfg").unwrap();
+        assert!(map.bindings(Action::Quit).is_empty());
+        assert_eq!(map.bindings(Action::Use), [Binding::Key(Key::Space), Binding::Mouse(MouseButton::Right)]);
+    }
+}
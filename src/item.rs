@@ -0,0 +1,6 @@
+#[derive(Clone, Copy, Debug)]
+pub struct Item {
+    pub x: f64,
+    pub y: f64,
+    pub collected: bool,
+}
@@ -0,0 +1,47 @@
+// Motor de raycasting: mapa, cámara (el jugador), trazado de rayos y renderizado
+// sobre un framebuffer en memoria. El binario solo añade la ventana y el audio.
+
+pub mod audio;
+pub mod door;
+pub mod enemy;
+pub mod framebuffer;
+pub mod game;
+pub mod input;
+pub mod item;
+pub mod lighting;
+pub mod map;
+pub mod maze;
+pub mod menu;
+pub mod parallel;
+pub mod pathfinding;
+pub mod placement;
+pub mod player;
+pub mod raycaster;
+pub mod renderer;
+pub mod replay;
+pub mod save;
+pub mod scaling;
+pub mod settings;
+pub mod shading;
+pub mod text;
+pub mod textures;
+
+pub use audio::{Audio, Channel, Emitter, GameSounds, Mix, Sound};
+pub use door::{Door, DoorState};
+pub use enemy::{Enemy, EnemyState};
+pub use framebuffer::Framebuffer;
+pub use game::{Game, GameEvent, Outcome};
+pub use item::Item;
+pub use lighting::{Light, Lightmap};
+pub use map::{Map, MapError, DOOR};
+pub use menu::{Menu, MenuEvent};
+pub use parallel::RenderPool;
+pub use player::Player;
+pub use raycaster::{cast_ray, has_line_of_sight, walls_between, RayHit};
+pub use renderer::{draw_minimap, draw_sprite, draw_sprites, render_scene, render_walls, Sprite};
+pub use replay::{MapSource, Replay, ReplayError, ReplayResult, Tick};
+pub use save::{SaveError, SaveGame};
+pub use scaling::{ScaleMode, Scaler, Viewport};
+pub use settings::Settings;
+pub use shading::{Fog, Shading};
+pub use textures::{load_sprite, Texture, WallTextures};
@@ -0,0 +1,157 @@
+use crate::map::{Map, DOOR};
+use crate::raycaster::has_line_of_sight;
+
+// Fuente de luz puntual: antorchas y lámparas del mapa o la luz que lleva el jugador
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    pub x: f64,
+    pub y: f64,
+    pub color: u32,
+    pub radius: f64,    // Distancia a la que la luz deja de alumbrar
+    pub intensity: f64, // Brillo en el centro, 1.0 deja la textura con su color original
+}
+
+impl Light {
+    pub fn new(x: f64, y: f64, color: u32, radius: f64, intensity: f64) -> Self {
+        Self { x, y, color, radius, intensity }
+    }
+
+    // Luz que aporta a un punto, sin comprobar si hay paredes en medio
+    fn contribution(&self, x: f64, y: f64) -> [f32; 3] {
+        let distance = (x - self.x).hypot(y - self.y);
+        if distance >= self.radius {
+            return [0.0; 3];
+        }
+        let falloff = (1.0 - distance / self.radius).powi(2) * self.intensity;
+        let channel = |shift: u32| (((self.color >> shift) & 0xFF) as f64 / 255.0 * falloff) as f32;
+        [channel(16), channel(8), channel(0)]
+    }
+}
+
+const MAX_LIGHT: f32 = 2.0; // Límite para que varias luces juntas no saturen del todo
+
+// Luz de cada celda del mapa. Las luces fijas se calculan una vez al cargar el nivel
+// y las móviles se suman encima en cada fotograma.
+#[derive(Default)]
+pub struct Lightmap {
+    width: usize,
+    height: usize,
+    enabled: bool, // Sin luces ni ambiente reducido todo queda con su color original
+    baked: Vec<[f32; 3]>,
+    cells: Vec<[f32; 3]>,
+}
+
+impl Lightmap {
+    pub fn bake(map: &Map) -> Self {
+        let ambient = map.ambient as f32;
+        let mut lightmap = Self {
+            width: map.width,
+            height: map.height,
+            enabled: !map.lights.is_empty() || map.player_light.is_some() || map.ambient != 1.0,
+            baked: vec![[ambient; 3]; map.width * map.height],
+            cells: Vec::new(),
+        };
+
+        let mut baked = std::mem::take(&mut lightmap.baked);
+        for light in &map.lights {
+            add_light(map, &mut baked, light);
+        }
+        lightmap.cells = baked.clone();
+        lightmap.baked = baked;
+        lightmap
+    }
+
+    // Recalcula la luz con las luces que se mueven (por ejemplo la del jugador)
+    pub fn update(&mut self, map: &Map, moving_lights: &[Light]) {
+        self.cells.copy_from_slice(&self.baked);
+        for light in moving_lights {
+            add_light(map, &mut self.cells, light);
+        }
+    }
+
+    // Luz en un punto del mapa, interpolada entre los centros de las celdas vecinas.
+    // Las paredes no cuentan para que sus bordes no oscurezcan el suelo de al lado.
+    pub fn sample(&self, map: &Map, x: f64, y: f64) -> [f32; 3] {
+        if !self.enabled {
+            return [1.0; 3];
+        }
+
+        let fx = x - 0.5;
+        let fy = y - 0.5;
+        let (x0, y0) = (fx.floor(), fy.floor());
+        let (tx, ty) = ((fx - x0) as f32, (fy - y0) as f32);
+
+        let mut light = [0.0; 3];
+        let mut total_weight = 0.0;
+        let corners = [
+            (0, 0, (1.0 - tx) * (1.0 - ty)),
+            (1, 0, tx * (1.0 - ty)),
+            (0, 1, (1.0 - tx) * ty),
+            (1, 1, tx * ty),
+        ];
+        for (dx, dy, weight) in corners {
+            let cx = x0 as isize + dx;
+            let cy = y0 as isize + dy;
+            if cx < 0 || cy < 0 || cx as usize >= self.width || cy as usize >= self.height || weight <= 0.0 {
+                continue;
+            }
+            let (cx, cy) = (cx as usize, cy as usize);
+            let cell = map.grid[cy][cx];
+            if cell > 0 && cell != DOOR {
+                continue;
+            }
+            let cell = self.cells[cy * self.width + cx];
+            for channel in 0..3 {
+                light[channel] += cell[channel] * weight;
+            }
+            total_weight += weight;
+        }
+
+        if total_weight <= 0.0 {
+            return self.cell_at(x, y);
+        }
+        light.map(|channel| (channel / total_weight).min(MAX_LIGHT))
+    }
+
+    fn cell_at(&self, x: f64, y: f64) -> [f32; 3] {
+        if x < 0.0 || y < 0.0 || x as usize >= self.width || y as usize >= self.height {
+            return [1.0; 3];
+        }
+        self.cells[y as usize * self.width + x as usize]
+    }
+
+    // Multiplica cada canal del color por la luz del punto
+    pub fn apply(&self, map: &Map, color: u32, x: f64, y: f64) -> u32 {
+        if !self.enabled {
+            return color;
+        }
+        modulate(color, self.sample(map, x, y))
+    }
+}
+
+pub fn modulate(color: u32, light: [f32; 3]) -> u32 {
+    let channel = |shift: u32, light: f32| ((((color >> shift) & 0xFF) as f32 * light) as u32).min(0xFF) << shift;
+    channel(16, light[0]) | channel(8, light[1]) | channel(0, light[2])
+}
+
+// Suma una luz a las celdas de su radio que la ven sin paredes en medio
+fn add_light(map: &Map, cells: &mut [[f32; 3]], light: &Light) {
+    let min_x = (light.x - light.radius).floor().max(0.0) as usize;
+    let min_y = (light.y - light.radius).floor().max(0.0) as usize;
+    let max_x = ((light.x + light.radius).ceil() as usize).min(map.width);
+    let max_y = ((light.y + light.radius).ceil() as usize).min(map.height);
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let center = (x as f64 + 0.5, y as f64 + 0.5);
+            let contribution = light.contribution(center.0, center.1);
+            if contribution == [0.0; 3] || !has_line_of_sight(map, (light.x, light.y), center) {
+                continue;
+            }
+            let cell = &mut cells[y * map.width + x];
+            for channel in 0..3 {
+                cell[channel] += contribution[channel];
+            }
+        }
+    }
+}
@@ -1,390 +1,627 @@
-use minifb::{Key, Window, WindowOptions};
+use minifb::{ScaleMode as WindowScaleMode, Window, WindowOptions};
 use std::time::{Duration, Instant};
-use rodio::{Decoder, OutputStream, Sink, Source};
-use std::fs::File;
-use std::io::BufReader;
+use std::env;
+use std::process;
 use rand::Rng;
-use image::GenericImageView;
 
-mod framebuffer;
-mod map;
-
-use framebuffer::Framebuffer;
-use map::{initialize_map, Map};
-
-mod player;
-use player::Player;
-
-mod raycaster;
-use raycaster::cast_ray;
-
-const WIDTH: usize = 640;
-const HEIGHT: usize = 480;
-const COLOR_CIELO: u32 = 0x87CEEB; // Celeste
-const COLOR_SUELO: u32 = 0x8B4513; // Café
+use raycasting::input::{Action, Input, InputMap};
+use raycasting::text::{draw_centered_text, draw_text_styled, draw_wrapped_text, Align, TextStyle};
+use raycasting::maze::MazeOptions;
+use raycasting::menu::dim;
+use raycasting::save::save_path;
+use raycasting::settings::MAX_VOLUME;
+use raycasting::{
+    draw_minimap, load_sprite, render_scene, render_walls, Framebuffer, Game, Light, Map, MapSource, Outcome, Player,
+    Audio, Channel, GameSounds, Menu, MenuEvent, RenderPool, Replay, ReplayResult, SaveGame, ScaleMode, Scaler,
+    Settings, Sprite, Tick, WallTextures,
+};
+
+const RENDER_WIDTH: usize = 640; // Resolución a la que se renderiza
+const RENDER_HEIGHT: usize = 480;
+const WINDOW_WIDTH: usize = 640; // Tamaño inicial de la ventana
+const WINDOW_HEIGHT: usize = 480;
+const DEFAULT_MAP: &str = "src/maps/level1.map";
+const DEFAULT_CONTROLS: &str = "src/controls.cfg";
+const MUSIC: &str = "src/musicanaruto.wav";
+const MAX_FRAME_TIME: f64 = 0.1; // Segundos
 
 enum GameState {
     WelcomeScreen,
     Playing,
+    Paused,
+    Settings,
     WinScreen,
+    GameOver,
 }
 
-struct Item {
-    x: f64,
-    y: f64,
-    collected: bool,
+const TITLE_ITEMS: [&str; 3] = ["New game", "Continue", "Quit"];
+const PAUSE_ITEMS: [&str; 5] = ["Resume", "Restart", "Save", "Settings", "Quit"];
+const GAME_OVER_ITEMS: [&str; 3] = ["Restart", "Title screen", "Quit"];
+
+const DEFAULT_MAZE_SIZE: usize = 25;
+
+const USAGE: &str = "Usage: HelloWindows [MAP] [--headless OUTPUT.png] [--pos X Y] [--dir DEGREES] [--size WIDTHxHEIGHT] [--threads N]
+                    [--window WIDTHxHEIGHT] [--scale integer|fit] [--controls FILE]
+                    [--record FILE] [--replay FILE [--verify]]
+                    [--generate backtracker|prim|kruskal] [--seed N] [--maze-size WIDTHxHEIGHT] [--braid F] [--rooms N]";
+
+// Opciones de la línea de comandos
+struct Options {
+    map_path: String,
+    maze: Option<MazeOptions>,       // Si existe, se genera el laberinto en vez de cargar el mapa
+    headless_output: Option<String>, // Si existe, se renderiza a PNG sin abrir ventana
+    position: Option<(f64, f64)>,
+    direction: Option<f64>, // En grados
+    size: (usize, usize),          // Resolución del render, también la del PNG con --headless
+    window_size: (usize, usize),   // Tamaño inicial de la ventana; luego se puede redimensionar
+    scale_mode: ScaleMode,
+    threads: usize, // Hilos de render; 0 uno por núcleo, 1 en serie
+    controls_path: Option<String>, // Si no se indica se usa DEFAULT_CONTROLS si existe
+    seed: u64,                     // Semilla del laberinto y de la posición de la llave y la meta
+    record_path: Option<String>,   // Si existe, se graba la partida en este archivo
+    replay_path: Option<String>,   // Si existe, se reproduce esta partida en vez de leer los controles
+    verify: bool,                  // Reproduce la partida sin ventana y comprueba el resultado
 }
 
-const FONT: [[u8; 5]; 21] = [
-    [0b01110, 0b10001, 0b10001, 0b10001, 0b01110], // 0
-    [0b00100, 0b01100, 0b00100, 0b00100, 0b01110], // 1
-    [0b01110, 0b10001, 0b00110, 0b01000, 0b11111], // 2
-    [0b01110, 0b10001, 0b00110, 0b10001, 0b01110], // 3
-    [0b00010, 0b00110, 0b01010, 0b11111, 0b00010], // 4
-    [0b11111, 0b10000, 0b11110, 0b00001, 0b11110], // 5
-    [0b01110, 0b10000, 0b11110, 0b10001, 0b01110], // 6
-    [0b11111, 0b00010, 0b00100, 0b01000, 0b10000], // 7
-    [0b01110, 0b10001, 0b01110, 0b10001, 0b01110], // 8
-    [0b01110, 0b10001, 0b01111, 0b00001, 0b01110], // 9
-    [0b11111, 0b10000, 0b11110, 0b10000, 0b10000], // F
-    [0b11110, 0b10001, 0b11110, 0b10000, 0b10000], // P
-    [0b01111, 0b10000, 0b01110, 0b00001, 0b11110], // S
-    [0b10001, 0b10001, 0b10101, 0b10101, 0b01010], // W
-    [0b01110, 0b10000, 0b11110, 0b10000, 0b01110], // E
-    [0b10000, 0b10000, 0b10000, 0b10000, 0b11110], // L
-    [0b01110, 0b10001, 0b10001, 0b10001, 0b01110], // O
-    [0b11110, 0b10001, 0b10001, 0b10001, 0b10001], // M
-    [0b10001, 0b11001, 0b10101, 0b10011, 0b10001], // N
-    [0b01010, 0b11111, 0b10001, 0b10001, 0b10001], // C
-    [0b11111, 0b00100, 0b00100, 0b00100, 0b11111], // I
-];
-
-fn draw_digit(framebuffer: &mut Framebuffer, x: usize, y: usize, index: usize, color: u32, scale: usize) {
-    if index >= FONT.len() { return; }
-
-    for (row, byte) in FONT[index].iter().enumerate() {
-        for col in 0..5 {
-            if byte & (1 << (4 - col)) != 0 {
-                for sy in 0..scale {
-                    for sx in 0..scale {
-                        framebuffer.point(x + col * scale + sx, y + row * scale + sy, color);
-                    }
-                }
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        map_path: DEFAULT_MAP.to_string(),
+        maze: None,
+        headless_output: None,
+        position: None,
+        direction: None,
+        size: (RENDER_WIDTH, RENDER_HEIGHT),
+        window_size: (WINDOW_WIDTH, WINDOW_HEIGHT),
+        scale_mode: ScaleMode::Integer,
+        threads: 0,
+        controls_path: None,
+        seed: 0,
+        record_path: None,
+        replay_path: None,
+        verify: false,
+    };
+
+    // Las opciones del laberinto pueden venir antes o después de `--generate`
+    let mut maze = MazeOptions::new(DEFAULT_MAZE_SIZE, DEFAULT_MAZE_SIZE, 0);
+    let mut generate = false;
+    let mut seed = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => options.headless_output = Some(next_value(&mut args, &arg)?),
+            "--pos" => {
+                let x = parse_number(&next_value(&mut args, &arg)?)?;
+                let y = parse_number(&next_value(&mut args, &arg)?)?;
+                options.position = Some((x, y));
+            }
+            "--dir" => options.direction = Some(parse_number(&next_value(&mut args, &arg)?)?),
+            "--size" => {
+                let value = next_value(&mut args, &arg)?;
+                options.size = parse_size(&value).ok_or(format!("invalid size `{}`", value))?;
+            }
+            "--window" => {
+                let value = next_value(&mut args, &arg)?;
+                options.window_size = parse_size(&value).ok_or(format!("invalid size `{}`", value))?;
             }
+            "--scale" => options.scale_mode = next_value(&mut args, &arg)?.parse()?,
+            "--threads" => {
+                let value = next_value(&mut args, &arg)?;
+                options.threads = value.parse().map_err(|_| format!("invalid thread count `{}`", value))?;
+            }
+            "--controls" => options.controls_path = Some(next_value(&mut args, &arg)?),
+            "--record" => options.record_path = Some(next_value(&mut args, &arg)?),
+            "--replay" => options.replay_path = Some(next_value(&mut args, &arg)?),
+            "--verify" => options.verify = true,
+            "--generate" => {
+                maze.algorithm = next_value(&mut args, &arg)?.parse()?;
+                generate = true;
+            }
+            "--seed" => {
+                let value = next_value(&mut args, &arg)?;
+                seed = Some(value.parse().map_err(|_| format!("invalid seed `{}`", value))?);
+            }
+            "--maze-size" => {
+                let value = next_value(&mut args, &arg)?;
+                (maze.width, maze.height) = parse_size(&value).ok_or(format!("invalid size `{}`", value))?;
+            }
+            "--braid" => maze.braid = parse_number(&next_value(&mut args, &arg)?)?.clamp(0.0, 1.0),
+            "--rooms" => {
+                let value = next_value(&mut args, &arg)?;
+                maze.rooms = value.parse().map_err(|_| format!("invalid room count `{}`", value))?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            _ => options.map_path = arg,
         }
     }
-}
 
-fn draw_text(framebuffer: &mut Framebuffer, x: usize, y: usize, text: &str, color: u32, scale: usize) {
-    let mut x_offset = 0;
-    for ch in text.chars() {
-        let index = match ch {
-            '0'..='9' => ch as usize - '0' as usize,
-            'F' => 10,
-            'P' => 11,
-            'S' => 12,
-            'W' => 13,
-            'E' => 14,
-            'L' => 15,
-            'O' => 16,
-            'M' => 17,
-            'N' => 18,
-            'C' => 19,
-            'I' => 20,
-            _ => continue,
-        };
-        draw_digit(framebuffer, x + x_offset, y, index, color, scale);
-        x_offset += 6 * scale;
+    if options.verify && options.replay_path.is_none() {
+        return Err("--verify needs --replay".to_string());
     }
-}
 
-fn draw_centered_text(framebuffer: &mut Framebuffer, text: &str, color: u32, scale: usize) {
-    let char_width = 6 * scale;
-    let char_height = 5 * scale;
-    let text_width = text.len() * char_width;
-    let text_height = char_height;
+    options.seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    if generate {
+        maze.seed = options.seed;
+        options.maze = Some(maze);
+    }
 
-    let x_start = (WIDTH - text_width) / 2;
-    let y_start = (HEIGHT - text_height) / 2;
+    Ok(options)
+}
 
-    draw_text(framebuffer, x_start, y_start, text, color, scale);
+fn next_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} expects a value", flag))
 }
 
-fn draw_fps(framebuffer: &mut Framebuffer, fps: usize) {
-    draw_text(framebuffer, WIDTH - 70, 10, &format!("{}FPS", fps), 0xFFFFFF, 1);
+fn parse_number(value: &str) -> Result<f64, String> {
+    value.parse().map_err(|_| format!("invalid number `{}`", value))
 }
 
-fn render_scene(map: &Map, player: &Player, framebuffer: &mut Framebuffer, wall_texture: &image::DynamicImage, key: &Item, goal: &Item) {
-    let texture_width = wall_texture.width() as usize;
-    let texture_height = wall_texture.height() as usize;
+fn parse_size(value: &str) -> Option<(usize, usize)> {
+    let (width, height) = value.split_once('x')?;
+    let width = width.parse().ok().filter(|&w| w > 0)?;
+    let height = height.parse().ok().filter(|&h| h > 0)?;
+    Some((width, height))
+}
 
-    for x in 0..framebuffer.width {
-        let camera_x = 2.0 * (x as f64) / (framebuffer.width as f64) - 1.0;
-        let ray_angle = player.direction + player.fov / 2.0 * camera_x;
+// Renderiza una vista del mapa y la guarda como PNG, sin ventana ni audio
+fn render_headless(map: &mut Map, options: &Options, pool: &RenderPool, output: &str) -> Result<(), String> {
+    let (x, y) = options.position.unwrap_or((map.spawn_x, map.spawn_y));
+    let direction = options.direction.map_or(map.spawn_direction, f64::to_radians);
+    let player = Player::new(x, y, direction);
+    map.update_lights(&player_lights(map, &player));
 
-        let ray_dir_x = ray_angle.cos();
-        let ray_dir_y = ray_angle.sin();
+    let wall_textures = WallTextures::load(map).map_err(|err| format!("failed to load wall textures: {}", err))?;
 
-        let (perp_wall_dist, is_horizontal) = cast_ray(map, player, player.fov / 2.0 * camera_x);
+    let (width, height) = options.size;
+    let mut framebuffer = Framebuffer::new(width, height);
+    render_walls(map, &player, &mut framebuffer, &wall_textures, pool);
 
-        if perp_wall_dist > 0.0 {
-            let wall_height = (framebuffer.height as f64 / (perp_wall_dist + 0.1)) as usize;
-            let wall_height = wall_height.min(framebuffer.height);
+    framebuffer
+        .save_png(output)
+        .map_err(|err| format!("failed to write {}: {}", output, err))
+}
 
-            let start = (framebuffer.height / 2).saturating_sub(wall_height / 2);
-            let end = (framebuffer.height / 2).saturating_add(wall_height / 2);
+// La luz del jugador, si el nivel tiene, se mueve con él
+fn player_lights(map: &Map, player: &Player) -> Vec<Light> {
+    map.player_light
+        .iter()
+        .map(|light| Light { x: player.x, y: player.y, ..*light })
+        .collect()
+}
 
-            // Renderizar cielo y suelo antes de la pared
-            for y in 0..start {
-                framebuffer.point(x, y, COLOR_CIELO);
-            }
-            for y in end..framebuffer.height {
-                framebuffer.point(x, y, COLOR_SUELO);
-            }
+// Controles del archivo indicado, o del archivo por defecto si existe, o los de serie
+fn load_controls(options: &Options) -> Result<InputMap, String> {
+    let path = match &options.controls_path {
+        Some(path) => path.as_str(),
+        None if std::path::Path::new(DEFAULT_CONTROLS).exists() => DEFAULT_CONTROLS,
+        None => return Ok(InputMap::default()),
+    };
+    InputMap::load_from_path(path).map_err(|err| format!("Failed to load controls {}: {}", path, err))
+}
 
-            // Calcular la posición del muro en la textura
-            let wall_x = if is_horizontal {
-                player.x + perp_wall_dist * ray_dir_x
-            } else {
-                player.y + perp_wall_dist * ray_dir_y
-            };
-            let wall_x = wall_x - wall_x.floor(); 
-
-            let tex_x = (wall_x * texture_width as f64).min(texture_width as f64 - 1.0) as usize;
-            for y in start..end {
-                let tex_y = (((y - start) * texture_height) / wall_height).min(texture_height - 1);
-                let pixel = wall_texture.get_pixel(tex_x as u32, tex_y as u32);
-                let color = ((pixel[0] as u32) << 16) | ((pixel[1] as u32) << 8) | (pixel[2] as u32);
-                framebuffer.point(x, y, color);
-            }
-        }
+// Reproduce una partida grabada sin ventana ni audio y devuelve cómo termina
+fn run_replay(replay: &Replay) -> Result<ReplayResult, String> {
+    let map = replay.map.load().map_err(|err| format!("Failed to load the replay map: {}", err))?;
+    let mut game = Game::new(map, replay.seed).map_err(|err| format!("Failed to place the key and goal: {}", err))?;
+    for tick in &replay.ticks {
+        game.step(tick);
     }
+    Ok(ReplayResult::from_game(&game))
+}
 
-    // Dibuja la llave si no ha sido recogida
-    if !key.collected {
-        draw_sprite(framebuffer, key.x, key.y, player, 0xFFFF00); // Color amarillo
+// Compara el final de una reproducción con el de la grabación; devuelve si coinciden
+fn report_replay(result: &ReplayResult, expected: Option<&ReplayResult>) -> bool {
+    println!("Replay finished: {}", result);
+    match expected {
+        Some(expected) if expected == result => {
+            println!("Replay matches the recording");
+            true
+        }
+        Some(expected) => {
+            println!("Replay does not match the recording, which ended {}", expected);
+            false
+        }
+        None => true,
     }
-
-    // Dibuja la meta
-    draw_sprite(framebuffer, goal.x, goal.y, player, 0x00FF00); // Color verde
 }
 
-// Función auxiliar para dibujar sprites o rectángulos
-fn draw_sprite(framebuffer: &mut Framebuffer, item_x: f64, item_y: f64, player: &Player, color: u32) {
-    let sprite_dist_x = item_x - player.x;
-    let sprite_dist_y = item_y - player.y;
-
-    // Calcular la proyección del sprite en la pantalla usando coseno y seno del ángulo de dirección
-    let inv_det = 1.0 / (player.plane_x * player.direction.sin() - player.direction.cos() * player.plane_y);
-
-    let transform_x = inv_det * (player.direction.sin() * sprite_dist_x - player.direction.cos() * sprite_dist_y);
-    let mut transform_y = inv_det * (-player.plane_y * sprite_dist_x + player.plane_x * sprite_dist_y);
-
-    // Evitar divisiones por cero o valores muy pequeños
-    if transform_y.abs() < f64::EPSILON {
-        transform_y = f64::EPSILON;
+fn load_map(source: &MapSource) -> Map {
+    match source.load() {
+        Ok(map) => map,
+        Err(err) => {
+            eprintln!("Failed to load map {}: {}", source, err);
+            process::exit(1);
+        }
     }
+}
 
-    let sprite_screen_x = ((framebuffer.width as f64 / 2.0) * (1.0 + transform_x / transform_y)) as isize;
-
-    // Tamaño y posición del sprite
-    let sprite_height = (framebuffer.height as f64 / transform_y) as isize;
-    let sprite_width = sprite_height;
-
-    // Calcular inicio y fin de dibujo, asegurando que no haya desbordamientos
-    let draw_start_y = (framebuffer.height as isize / 2 - sprite_height / 2).max(0);
-    let draw_start_x = sprite_screen_x.saturating_sub(sprite_width / 2).max(0) as usize;
-    let draw_end_x = draw_start_x.saturating_add(sprite_width as usize).min(framebuffer.width);
-    let draw_end_y = (draw_start_y + sprite_height).min(framebuffer.height as isize);
-
-    // Dibujar el rectángulo del sprite, evitando fuera de rango
-    for x in draw_start_x..draw_end_x {
-        for y in draw_start_y..draw_end_y {
-            if x < framebuffer.width && (y as usize) < framebuffer.height {
-                framebuffer.point(x, y as usize, color);
-            }
+fn start_game(map: Map, seed: u64, settings: &Settings) -> Game {
+    let mut game = match Game::new(map, seed) {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("Failed to place the key and goal: {}", err);
+            process::exit(1);
         }
+    };
+    game.player.fov = settings.fov.to_radians();
+    game
+}
+
+// Empieza otra partida en el mismo mapa, con la llave y la meta en otro sitio. Si se está
+// grabando, se guarda la partida que termina y se empieza a grabar la nueva.
+fn restart(
+    game: &mut Game,
+    audio: &mut Audio,
+    sounds: &mut GameSounds,
+    recording: &mut Option<Replay>,
+    source: &MapSource,
+    settings: &Settings,
+    options: &Options,
+) {
+    let seed = rand::thread_rng().gen();
+    if let Some(recording) = recording.as_mut() {
+        save_recording(recording, game, options);
+        *recording = Replay::new(source.clone(), seed);
     }
+    *game = start_game(load_map(source), seed, settings);
+    sounds.reset(audio, game);
 }
 
+// El archivo de la grabación se queda con la última partida que se haya llegado a jugar
+fn save_recording(recording: &mut Replay, game: &Game, options: &Options) {
+    let Some(path) = &options.record_path else {
+        return;
+    };
+    if recording.ticks.is_empty() {
+        return;
+    }
+    recording.result = Some(ReplayResult::from_game(game));
+    match recording.save(path) {
+        Ok(()) => println!("Saved replay of {} ticks to {}", recording.ticks.len(), path),
+        Err(err) => eprintln!("Failed to save replay {}: {}", path, err),
+    }
+}
 
+// Nombre de la primera tecla asignada a la acción, para los mensajes en pantalla
+fn binding_name(controls: &InputMap, action: Action) -> String {
+    match controls.bindings(action).first() {
+        Some(binding) => binding.to_string(),
+        None => action.to_string(),
+    }
+}
 
+// Muestra el framebuffer escalado al tamaño actual de la ventana
+fn show(window: &mut Window, scaler: &mut Scaler, framebuffer: &Framebuffer, mode: ScaleMode) {
+    let (width, height) = window.get_size();
+    if width == 0 || height == 0 {
+        // Ventana minimizada: solo se atienden sus eventos
+        window.update();
+        return;
+    }
+    let buffer = scaler.present(framebuffer, width, height, mode);
+    window.update_with_buffer(buffer, width, height).unwrap();
+}
 
+fn draw_fps(framebuffer: &mut Framebuffer, fps: usize) {
+    let mut style = TextStyle::new(0xFFFFFF, 1);
+    style.align = Align::Right;
+    style.shadow = Some(0x000000);
+    let x = framebuffer.width.saturating_sub(10);
+    draw_text_styled(framebuffer, x, 10, &format!("{} FPS", fps), &style);
+}
 
+// Pantalla de inicio: el menú principal y una explicación del juego
+fn draw_welcome(framebuffer: &mut Framebuffer, menu: &Menu) {
+    menu.draw(framebuffer, "3D Raycaster", &TITLE_ITEMS.map(String::from));
+
+    let mut style = TextStyle::new(0xA0A0A0, 1);
+    style.align = Align::Center;
+    let margin = framebuffer.width / 6;
+    draw_wrapped_text(
+        framebuffer,
+        margin,
+        framebuffer.height * 3 / 4,
+        framebuffer.width - 2 * margin,
+        "Find the key, then reach the goal. Open doors with Use and keep away from the ghosts: if one catches you, the game is over.",
+        &style,
+    );
+}
 
+// Mensaje bajo los menús, por ejemplo el resultado de guardar o cargar la partida
+fn draw_notice(framebuffer: &mut Framebuffer, notice: &str) {
+    let mut style = TextStyle::new(0xFFD040, 1);
+    style.align = Align::Center;
+    style.shadow = Some(0x000000);
+    // Con resoluciones muy pequeñas el aviso se queda arriba en vez de salirse del framebuffer
+    let margin = framebuffer.width / 10;
+    let y = framebuffer.height.saturating_sub(6 * style.line_height());
+    let width = framebuffer.width.saturating_sub(2 * margin);
+    draw_wrapped_text(framebuffer, margin, y, width, notice, &style);
+}
 
-fn draw_minimap(map: &Map, player: &Player, framebuffer: &mut Framebuffer, key: &Item, goal: &Item) {
-    let minimap_scale = 4;
+// Minutos y segundos, para mostrar el tiempo de juego
+fn format_time(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).floor();
+    format!("{}:{:04.1}", minutes, seconds - minutes * 60.0)
+}
 
-    for y in 0..map.height {
-        for x in 0..map.width {
-            let color = if map.is_wall(x as f64, y as f64) {
-                0xFFFFFF
-            } else {
-                0x000000
-            };
+fn save_game(game: &Game, source: &MapSource) -> Result<String, String> {
+    let path = save_path().map_err(|err| format!("Could not save the game: {}", err))?;
+    SaveGame::from_game(game, source)
+        .save(&path)
+        .map_err(|err| format!("Could not save the game to {}: {}", path.display(), err))?;
+    Ok(format!("Game saved to {}", path.display()))
+}
 
-            for py in 0..minimap_scale {
-                for px in 0..minimap_scale {
-                    framebuffer.point(x * minimap_scale + px, y * minimap_scale + py, color);
-                }
-            }
-        }
+// Carga la partida guardada y devuelve también de qué mapa es
+fn load_game() -> Result<(MapSource, Game), String> {
+    let path = save_path().map_err(|err| format!("Could not load the saved game: {}", err))?;
+    if !path.exists() {
+        return Err("There is no saved game yet".to_string());
     }
+    let error = |err| format!("Could not load the saved game {}: {}", path.display(), err);
+    let save = SaveGame::load_from_path(&path).map_err(error)?;
+    let game = save.restore().map_err(error)?;
+    Ok((save.map, game))
+}
 
-    let player_x = (player.x * minimap_scale as f64) as usize;
-    let player_y = (player.y * minimap_scale as f64) as usize;
-    for py in 0..minimap_scale {
-        for px in 0..minimap_scale {
-            framebuffer.point(player_x + px, player_y + py, 0xFF0000);
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            process::exit(2);
         }
-    }
+    };
 
-    if !key.collected {
-        let key_x = (key.x * minimap_scale as f64) as usize;
-        let key_y = (key.y * minimap_scale as f64) as usize;
-        for py in 0..minimap_scale {
-            for px in 0..minimap_scale {
-                framebuffer.point(key_x + px, key_y + py, 0xFFFF00);
+    let replay = options.replay_path.as_ref().map(|path| match Replay::load_from_path(path) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("Failed to load replay {}: {}", path, err);
+            process::exit(1);
+        }
+    });
+
+    if options.verify {
+        let replay = replay.as_ref().unwrap();
+        match run_replay(replay) {
+            Ok(result) => process::exit(if report_replay(&result, replay.result.as_ref()) { 0 } else { 1 }),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
             }
         }
     }
 
-    let goal_x = (goal.x * minimap_scale as f64) as usize;
-    let goal_y = (goal.y * minimap_scale as f64) as usize;
-    for py in 0..minimap_scale {
-        for px in 0..minimap_scale {
-            framebuffer.point(goal_x + px, goal_y + py, 0x00FF00);
-        }
+    // Una repetición trae su propio mapa y su semilla
+    let (mut map_source, seed) = match &replay {
+        Some(replay) => (replay.map.clone(), replay.seed),
+        None => match &options.maze {
+            Some(maze) => (MapSource::Maze(maze.clone()), options.seed),
+            None => (MapSource::File(options.map_path.clone()), options.seed),
+        },
+    };
+
+    if let MapSource::Maze(maze) = &map_source {
+        // Se muestra la semilla para poder reproducir el mismo laberinto
+        println!("Generating {} maze with seed {}", maze.algorithm, maze.seed);
     }
+    let mut map = load_map(&map_source);
 
-    
-}
-
-fn generate_random_position(map: &Map) -> (f64, f64) {
-    let mut rng = rand::thread_rng();
-    let mut x;
-    let mut y;
-
-    loop {
-        x = rng.gen_range(1..(map.width - 1)) as f64;
-        y = rng.gen_range(1..(map.height - 1)) as f64;
+    let pool = match RenderPool::new(options.threads) {
+        Ok(pool) => pool,
+        Err(err) => {
+            eprintln!("Failed to start {} render threads: {}", options.threads, err);
+            process::exit(1);
+        }
+    };
 
-        if !map.is_wall(x, y) {
-            break;
+    if let Some(output) = &options.headless_output {
+        if let Err(err) = render_headless(&mut map, &options, &pool, output) {
+            eprintln!("{}", err);
+            process::exit(1);
         }
+        return;
     }
 
-    (x, y)
-}
-
-fn main() {
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-
-    let music_sink = Sink::try_new(&stream_handle).unwrap();
-    let music_file = BufReader::new(File::open("src/musicanaruto.wav").unwrap());
-    let music_source = Decoder::new(music_file).unwrap();
-    music_sink.append(music_source.repeat_infinite());
-
-    let walk_sink = Sink::try_new(&stream_handle).unwrap();
+    // Sin dispositivo de audio o sin los archivos de sonido el juego sigue, en silencio
+    let mut audio = Audio::new();
+    let music = Channel::new(&audio);
+    music.play(audio.sound(MUSIC).as_ref(), true);
 
-    let mut volume = 0.5;
-    music_sink.set_volume(volume);
-    walk_sink.set_volume(0.5);
+    let mut settings = Settings { scale_mode: options.scale_mode, ..Settings::default() };
+    music.set_volume(settings.volume);
 
-    music_sink.play();
-
-    let map = initialize_map();
-    let mut player = Player::new(12.0, 12.0, 0.0);
-
-    let wall_texture = image::open("src/wall_texture.png").expect("Failed to load wall texture");
-
-    let window_width = WIDTH;
-    let window_height = HEIGHT;
+    let mut wall_textures = WallTextures::load(&map).expect("Failed to load wall textures");
+    let key_image = load_sprite("src/key_sprite.png").expect("Failed to load key sprite");
+    let goal_image = load_sprite("src/goal_sprite.png").expect("Failed to load goal sprite");
+    let enemy_image = load_sprite("src/enemy_sprite.png").expect("Failed to load enemy sprite");
 
     let target_fps = 60;
     let frame_duration = Duration::from_secs_f64(1.0 / target_fps as f64);
 
-    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    // El juego se renderiza a `options.size` y se escala a lo que mida la ventana en cada momento
+    let (render_width, render_height) = options.size;
+    let mut framebuffer = Framebuffer::new(render_width, render_height);
+    let mut scaler = Scaler::new();
+    let (window_width, window_height) = options.window_size;
     let mut window = Window::new(
         "3D Raycaster Press enter for continue",
         window_width,
         window_height,
-        WindowOptions::default(),
+        WindowOptions { resize: true, scale_mode: WindowScaleMode::UpperLeft, ..WindowOptions::default() },
     )
     .unwrap();
 
-    let mut last_mouse_x = WIDTH as f32 / 2.0;
-    let mut game_state = GameState::WelcomeScreen;
+    let mut input = match load_controls(&options) {
+        Ok(controls) => Input::new(controls),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    let mut last_mouse_x = window_width as f32 / 2.0;
 
     let mut last_time = Instant::now();
+    let mut last_frame = Instant::now();
     let mut frame_count = 0;
     let mut fps = 0;
 
-    let (key_x, key_y) = generate_random_position(&map);
-    let (goal_x, goal_y) = generate_random_position(&map);
+    let mut game = start_game(map, seed, &settings);
+    let mut sounds = GameSounds::new(&mut audio, &game, settings.volume);
+
+    let mut recording = options.record_path.as_ref().map(|_| Replay::new(map_source.clone(), seed));
+    let expected_result = replay.as_ref().and_then(|replay| replay.result);
+    let mut playback = replay.map(|replay| replay.ticks.into_iter());
+
+    // Al reproducir una partida se empieza a jugar directamente
+    let mut game_state = if playback.is_some() { GameState::Playing } else { GameState::WelcomeScreen };
+
+    let mut menu = Menu::new();
+    // Último fotograma de la partida, oscurecido, que se ve detrás de los menús
+    let mut menu_background = vec![0; render_width * render_height];
+    let mut notice = String::new();
 
-    let mut key = Item { x: key_x, y: key_y, collected: false };
-    let mut goal = Item { x: goal_x, y: goal_y, collected: false };
+    while window.is_open() {
+        input.update(&window);
+        if input.is_down(Action::Quit) {
+            break;
+        }
 
-    while window.is_open() && !window.is_key_down(Key::Escape) {
         match game_state {
             GameState::WelcomeScreen => {
                 const COLOR_FONDO: u32 = 0x000000;
                 framebuffer.buffer.fill(COLOR_FONDO);
-                draw_centered_text(&mut framebuffer, "WELCOME", 0xFFFFFF, 3);
-                window.update_with_buffer(&framebuffer.buffer, WIDTH, HEIGHT).unwrap();
-
-                if window.is_key_down(Key::Enter) {
-                    game_state = GameState::Playing;
+                draw_welcome(&mut framebuffer, &menu);
+                draw_notice(&mut framebuffer, &notice);
+                show(&mut window, &mut scaler, &framebuffer, settings.scale_mode);
+
+                match (menu.update(&input, TITLE_ITEMS.len()), menu.selected) {
+                    (MenuEvent::Activate, 0) => game_state = GameState::Playing,
+                    (MenuEvent::Activate, 1) => match load_game() {
+                        Ok((source, loaded)) => match WallTextures::load(&loaded.map) {
+                            Ok(textures) => {
+                                if let Some(recording) = recording.as_mut() {
+                                    save_recording(recording, &game, &options);
+                                    eprintln!("Stopped recording: a loaded game cannot be replayed");
+                                }
+                                recording = None;
+                                map_source = source;
+                                wall_textures = textures;
+                                game = loaded;
+                                sounds.reset(&mut audio, &game);
+                                settings.fov = game.player.fov.to_degrees();
+                                game_state = GameState::Playing;
+                            }
+                            Err(err) => {
+                                notice = format!("Failed to load wall textures: {}", err);
+                                eprintln!("{}", notice);
+                            }
+                        },
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            notice = err;
+                        }
+                    },
+                    (MenuEvent::Activate, 2) => break,
+                    _ => {}
+                }
+                if let GameState::Playing = game_state {
+                    notice.clear();
                     last_time = Instant::now();
+                    last_frame = last_time;
                     frame_count = 0;
+                    if let Some((mouse_x, _)) = window.get_mouse_pos(minifb::MouseMode::Pass) {
+                        last_mouse_x = mouse_x;
+                    }
                 }
+                std::thread::sleep(frame_duration);
             }
             GameState::Playing => {
+                if input.was_pressed(Action::Pause) {
+                    game_state = GameState::Paused;
+                    menu.selected = 0;
+                    notice.clear();
+                    dim(&mut framebuffer);
+                    menu_background.copy_from_slice(&framebuffer.buffer);
+                    continue;
+                }
+
                 let start_time = Instant::now();
+                // Tiempo desde el fotograma anterior, limitado para que una pausa larga
+                // (por ejemplo al mover la ventana) no provoque un salto
+                let dt = start_time.duration_since(last_frame).as_secs_f64().min(MAX_FRAME_TIME);
+                last_frame = start_time;
                 const COLOR_FONDO: u32 =  0x000000;
                 framebuffer.buffer.fill(COLOR_FONDO);
 
-                if window.is_key_down(Key::W) || window.is_key_down(Key::Up) {
-                    player.move_forward(0.05, &map, &walk_sink);
-                }
-                if window.is_key_down(Key::S) || window.is_key_down(Key::Down) {
-                    player.move_backward(0.05, &map, &walk_sink);
+                // Al reproducir, las acciones salen de la grabación en vez de la ventana
+                let tick = match playback.as_mut() {
+                    Some(ticks) => ticks.next(),
+                    None => {
+                        let mut mouse_turn = 0.0;
+                        if let Some((mouse_x, _)) = window.get_mouse_pos(minifb::MouseMode::Pass) {
+                            mouse_turn = (mouse_x - last_mouse_x) as f64 * settings.mouse_sensitivity;
+                            last_mouse_x = mouse_x;
+                        }
+                        Some(Tick { dt, down: input.down_mask(), pressed: input.pressed_mask(), mouse_turn })
+                    }
+                };
+                match tick {
+                    Some(tick) => {
+                        game.step(&tick);
+                        sounds.update(&mut audio, &game, &game.events);
+                        if let Some(recording) = recording.as_mut() {
+                            recording.ticks.push(tick);
+                        }
+                    }
+                    None => {
+                        // Fin de la grabación: se informa y el jugador sigue desde ahí
+                        report_replay(&ReplayResult::from_game(&game), expected_result.as_ref());
+                        playback = None;
+                        if let Some((mouse_x, _)) = window.get_mouse_pos(minifb::MouseMode::Pass) {
+                            last_mouse_x = mouse_x;
+                        }
+                    }
                 }
-                if window.is_key_down(Key::A) || window.is_key_down(Key::Left) {
-                    player.turn_left(0.03);
+                match game.outcome {
+                    Outcome::Playing => {}
+                    Outcome::Won => game_state = GameState::WinScreen,
+                    Outcome::Lost => {
+                        game_state = GameState::GameOver;
+                        menu.selected = 0;
+                    }
                 }
-                if window.is_key_down(Key::D) || window.is_key_down(Key::Right) {
-                    player.turn_right(0.03);
+                if game.outcome != Outcome::Playing && playback.take().is_some() {
+                    report_replay(&ReplayResult::from_game(&game), expected_result.as_ref());
                 }
 
-                if window.is_key_down(Key::Equal) {
-                    volume = (volume + 0.001).min(4.0);
-                    music_sink.set_volume(volume);
-                }
-                if window.is_key_down(Key::Minus) {
-                    volume = (volume - 0.001).max(0.0);
-                    music_sink.set_volume(volume);
-                }
+                let moving_lights = player_lights(&game.map, &game.player);
+                game.map.update_lights(&moving_lights);
 
-                if let Some((mouse_x, _)) = window.get_mouse_pos(minifb::MouseMode::Pass) {
-                    let mouse_delta = mouse_x - last_mouse_x;
-                    player.turn_right(mouse_delta as f64 * 0.002);
-                    last_mouse_x = mouse_x;
+                if input.is_down(Action::VolumeUp) {
+                    settings.volume = (settings.volume + 0.001).min(MAX_VOLUME);
+                    music.set_volume(settings.volume);
+                    sounds.set_volume(settings.volume);
                 }
-
-                if (player.x - key.x).abs() < 0.5 && (player.y - key.y).abs() < 0.5 {
-                    key.collected = true;
+                if input.is_down(Action::VolumeDown) {
+                    settings.volume = (settings.volume - 0.001).max(0.0);
+                    music.set_volume(settings.volume);
+                    sounds.set_volume(settings.volume);
                 }
 
-                if key.collected && (player.x - goal.x).abs() < 0.5 && (player.y - goal.y).abs() < 0.5 {
-                    game_state = GameState::WinScreen;
+                // La llave solo se dibuja si no ha sido recogida
+                let (key, goal) = (&game.key, &game.goal);
+                let mut sprites = vec![Sprite { x: goal.x, y: goal.y, image: &goal_image }];
+                if !key.collected {
+                    sprites.push(Sprite { x: key.x, y: key.y, image: &key_image });
                 }
+                for enemy in &game.map.enemies {
+                    sprites.push(Sprite { x: enemy.x, y: enemy.y, image: &enemy_image });
+                }
+                render_scene(&game.map, &game.player, &mut framebuffer, &wall_textures, &sprites, &pool);
 
-                render_scene(&map, &player, &mut framebuffer, &wall_texture, &key, &goal);
-
-                draw_minimap(&map, &player, &mut framebuffer, &key, &goal);
+                draw_minimap(&game.map, &game.player, &mut framebuffer, key, goal);
 
                 frame_count += 1;
                 let current_time = Instant::now();
@@ -396,28 +633,153 @@ fn main() {
 
                 draw_fps(&mut framebuffer, fps);
 
-                let mut display_buffer = vec![COLOR_FONDO; window_width * window_height];
-                for y in 0..HEIGHT {
-                    for x in 0..WIDTH {
-                        if framebuffer.is_point_set(x, y) {
-                            display_buffer[y * window_width + x] = framebuffer.buffer[y * WIDTH + x];
-                        }
-                    }
-                }
+                show(&mut window, &mut scaler, &framebuffer, settings.scale_mode);
 
-                window.update_with_buffer(&display_buffer, window_width, window_height).unwrap();
+                if game.outcome == Outcome::Lost {
+                    dim(&mut framebuffer);
+                    menu_background.copy_from_slice(&framebuffer.buffer);
+                }
 
                 let elapsed_time = start_time.elapsed();
                 if frame_duration > elapsed_time {
                     std::thread::sleep(frame_duration - elapsed_time);
                 }
             }
+            GameState::Paused => {
+                sounds.pause();
+                framebuffer.buffer.copy_from_slice(&menu_background);
+                menu.draw(&mut framebuffer, "PAUSE", &PAUSE_ITEMS.map(String::from));
+                draw_notice(&mut framebuffer, &notice);
+                show(&mut window, &mut scaler, &framebuffer, settings.scale_mode);
+
+                match (menu.update(&input, PAUSE_ITEMS.len()), menu.selected) {
+                    (MenuEvent::Back, _) | (MenuEvent::Activate, 0) => game_state = GameState::Playing,
+                    (MenuEvent::Activate, 1) => {
+                        restart(&mut game, &mut audio, &mut sounds, &mut recording, &map_source, &settings, &options);
+                        playback = None;
+                        game_state = GameState::Playing;
+                    }
+                    (MenuEvent::Activate, 2) => match save_game(&game, &map_source) {
+                        Ok(message) => {
+                            println!("{}", message);
+                            notice = message;
+                        }
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            notice = err;
+                        }
+                    },
+                    (MenuEvent::Activate, 3) => {
+                        game_state = GameState::Settings;
+                        menu.selected = 0;
+                    }
+                    (MenuEvent::Activate, 4) => break,
+                    _ => {}
+                }
+                if let GameState::Playing = game_state {
+                    notice.clear();
+                    // Que el tiempo y el ratón en pausa no cuenten al volver
+                    last_frame = Instant::now();
+                    if let Some((mouse_x, _)) = window.get_mouse_pos(minifb::MouseMode::Pass) {
+                        last_mouse_x = mouse_x;
+                    }
+                }
+                std::thread::sleep(frame_duration);
+            }
+            GameState::Settings => {
+                sounds.pause();
+                let items = [
+                    format!("Volume  {:.0}%", settings.volume * 100.0),
+                    format!("Mouse sensitivity  {:.1}", settings.mouse_sensitivity * 1000.0),
+                    format!("Field of view  {:.0}", settings.fov),
+                    format!("Scaling  {}", settings.scale_mode),
+                    "Back".to_string(),
+                ];
+                framebuffer.buffer.copy_from_slice(&menu_background);
+                menu.draw(&mut framebuffer, "SETTINGS", &items);
+                show(&mut window, &mut scaler, &framebuffer, settings.scale_mode);
+
+                let steps = match menu.update(&input, items.len()) {
+                    MenuEvent::Decrease => -1,
+                    MenuEvent::Increase => 1,
+                    MenuEvent::Activate if menu.selected == 4 => {
+                        game_state = GameState::Paused;
+                        menu.selected = 3;
+                        0
+                    }
+                    MenuEvent::Back => {
+                        game_state = GameState::Paused;
+                        menu.selected = 3;
+                        0
+                    }
+                    _ => 0,
+                };
+                if steps != 0 {
+                    match menu.selected {
+                        0 => {
+                            settings.change_volume(steps);
+                            music.set_volume(settings.volume);
+                            sounds.set_volume(settings.volume);
+                        }
+                        1 => settings.change_mouse_sensitivity(steps),
+                        2 => {
+                            settings.change_fov(steps);
+                            game.player.fov = settings.fov.to_radians();
+                        }
+                        3 => settings.change_scale_mode(steps),
+                        _ => {}
+                    }
+                }
+                std::thread::sleep(frame_duration);
+            }
             GameState::WinScreen => {
+                sounds.pause();
                 const COLOR_FONDO: u32 =  0x000000;
                 framebuffer.buffer.fill(COLOR_FONDO);
                 draw_centered_text(&mut framebuffer, "WIN", 0x00FF00, 4);
-                window.update_with_buffer(&framebuffer.buffer, WIDTH, HEIGHT).unwrap();
+                let mut style = TextStyle::new(0xFFFFFF, 2);
+                style.align = Align::Center;
+                let confirm = binding_name(&input.map, Action::Confirm);
+                let message = format!("Time {}\n\nPress {} to return to the title", format_time(game.elapsed), confirm);
+                draw_text_styled(&mut framebuffer, render_width / 2, render_height * 2 / 3, &message, &style);
+                show(&mut window, &mut scaler, &framebuffer, settings.scale_mode);
+
+                if input.was_pressed(Action::Confirm) {
+                    restart(&mut game, &mut audio, &mut sounds, &mut recording, &map_source, &settings, &options);
+                    game_state = GameState::WelcomeScreen;
+                    menu.selected = 0;
+                }
+                std::thread::sleep(frame_duration);
+            }
+            GameState::GameOver => {
+                sounds.pause();
+                framebuffer.buffer.copy_from_slice(&menu_background);
+                menu.draw(&mut framebuffer, "GAME OVER", &GAME_OVER_ITEMS.map(String::from));
+                show(&mut window, &mut scaler, &framebuffer, settings.scale_mode);
+
+                match (menu.update(&input, GAME_OVER_ITEMS.len()), menu.selected) {
+                    (MenuEvent::Activate, 0) => {
+                        restart(&mut game, &mut audio, &mut sounds, &mut recording, &map_source, &settings, &options);
+                        game_state = GameState::Playing;
+                        last_frame = Instant::now();
+                        if let Some((mouse_x, _)) = window.get_mouse_pos(minifb::MouseMode::Pass) {
+                            last_mouse_x = mouse_x;
+                        }
+                    }
+                    (MenuEvent::Activate, 1) => {
+                        restart(&mut game, &mut audio, &mut sounds, &mut recording, &map_source, &settings, &options);
+                        game_state = GameState::WelcomeScreen;
+                        menu.selected = 0;
+                    }
+                    (MenuEvent::Activate, 2) => break,
+                    _ => {}
+                }
+                std::thread::sleep(frame_duration);
             }
         }
     }
+
+    if let Some(recording) = recording.as_mut() {
+        save_recording(recording, &game, &options);
+    }
 }
@@ -1,67 +1,631 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::door::Door;
+use crate::enemy::Enemy;
+use crate::lighting::{Light, Lightmap};
+use crate::player::Player;
+use crate::shading::{Fog, Shading};
+
+pub const DOOR: i32 = 10; // Tipo de celda de las puertas, `D` en los archivos de mapa
+
 pub struct Map {
     pub width: usize,
     pub height: usize,
-    pub grid: Vec<Vec<i32>>, // 1 es pared, 0 es espacio vacío
+    pub grid: Vec<Vec<i32>>, // >0 es el tipo de pared, 0 es espacio vacío
+    pub floor: Vec<Vec<i32>>,   // Textura del suelo de cada celda, 0 es color plano
+    pub ceiling: Vec<Vec<i32>>, // Textura del techo de cada celda, 0 es color plano
+    pub textures: HashMap<i32, String>, // Imagen de cada tipo de celda
+    pub doors: HashMap<(usize, usize), Door>, // Estado de cada celda de puerta
+    pub enemies: Vec<Enemy>, // Enemigos del nivel con su estado actual
+    pub shading: Shading,    // Niebla y sombreado de las caras
+    pub lights: Vec<Light>,  // Luces fijas del nivel
+    pub player_light: Option<Light>, // Luz que lleva el jugador; su posición se toma del jugador
+    pub ambient: f64,        // Luz que reciben todas las celdas, 1.0 sin oscurecer
+    pub lightmap: Lightmap,
+    pub spawn_x: f64,         // Posición inicial del jugador
+    pub spawn_y: f64,
+    pub spawn_direction: f64, // Dirección inicial en radianes
+}
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(std::io::Error),
+    Parse { line: usize, column: usize, message: String },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Io(err) => write!(f, "{}", err),
+            MapError::Parse { line, column, message } => {
+                write!(f, "line {}, column {}: {}", line, column, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+impl From<std::io::Error> for MapError {
+    fn from(err: std::io::Error) -> Self {
+        MapError::Io(err)
+    }
+}
+
+fn parse_error(line: usize, column: usize, message: impl Into<String>) -> MapError {
+    MapError::Parse { line, column, message: message.into() }
 }
 
 impl Map {
     pub fn new(width: usize, height: usize) -> Self {
         let grid = vec![vec![0; width]; height];
-        Self { width, height, grid }
+        Self {
+            width,
+            height,
+            floor: grid.clone(),
+            ceiling: grid.clone(),
+            grid,
+            textures: HashMap::new(),
+            doors: HashMap::new(),
+            enemies: Vec::new(),
+            shading: Shading::default(),
+            lights: Vec::new(),
+            player_light: None,
+            ambient: 1.0,
+            lightmap: Lightmap::default(),
+            spawn_x: width as f64 / 2.0,
+            spawn_y: height as f64 / 2.0,
+            spawn_direction: 0.0,
+        }
+    }
+
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Map, MapError> {
+        let contents = fs::read_to_string(path)?;
+        contents.parse()
     }
 
+    // Las puertas solo bloquean el paso mientras no estén lo bastante abiertas
     pub fn is_wall(&self, x: f64, y: f64) -> bool {
+        match self.wall_at(x, y) {
+            DOOR => !self.door_at(x, y).is_some_and(Door::is_passable),
+            wall => wall > 0,
+        }
+    }
+
+    pub fn door_at(&self, x: f64, y: f64) -> Option<&Door> {
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        self.doors.get(&(x as usize, y as usize))
+    }
+
+    // Acciona la puerta de la celda; devuelve true solo si ha empezado a abrirse
+    pub fn use_door(&mut self, x: f64, y: f64) -> bool {
+        if x < 0.0 || y < 0.0 {
+            return false;
+        }
+        self.doors.get_mut(&(x as usize, y as usize)).is_some_and(|door| door.activate())
+    }
+
+    // Anima todas las puertas; no se cierran las que toca el cuadrado de colisión del jugador
+    // ni las que tienen un enemigo dentro. Devuelve las celdas de las puertas que han empezado
+    // a moverse solas (a cerrarse, o a abrirse otra vez porque algo las bloquea), ordenadas.
+    pub fn update_doors(&mut self, dt: f64, player_x: f64, player_y: f64, player_radius: f64) -> Vec<(usize, usize)> {
+        let min_x = (player_x - player_radius).floor();
+        let max_x = (player_x + player_radius).floor();
+        let min_y = (player_y - player_radius).floor();
+        let max_y = (player_y + player_radius).floor();
+
+        let mut moved = Vec::new();
+        for (&cell, door) in self.doors.iter_mut() {
+            let (x, y) = (cell.0 as f64, cell.1 as f64);
+            let occupied = (x >= min_x && x <= max_x && y >= min_y && y <= max_y)
+                || self.enemies.iter().any(|enemy| enemy.x.floor() == x && enemy.y.floor() == y);
+            if door.update(dt, occupied) {
+                moved.push(cell);
+            }
+        }
+        moved.sort_by_key(|&(x, y)| (y, x));
+        moved
+    }
+
+    // Mueve a los enemigos; devuelve true si alguno ha atrapado al jugador
+    pub fn update_enemies(&mut self, dt: f64, player_x: f64, player_y: f64) -> bool {
+        // Se sacan del mapa mientras se actualizan porque necesitan consultarlo
+        let mut enemies = std::mem::take(&mut self.enemies);
+        for enemy in &mut enemies {
+            enemy.update(dt, self, player_x, player_y);
+        }
+        self.enemies = enemies;
+
+        self.enemies.iter().any(|enemy| enemy.has_caught(player_x, player_y))
+    }
+
+    // Vuelve a calcular la luz de las celdas con las luces fijas; hace falta tras cambiar
+    // las luces, el ambiente o las paredes
+    pub fn bake_lights(&mut self) {
+        self.lightmap = Lightmap::bake(self);
+    }
+
+    // Suma al mapa de luz horneado las luces que se han movido en este fotograma
+    pub fn update_lights(&mut self, moving_lights: &[Light]) {
+        let mut lightmap = std::mem::take(&mut self.lightmap);
+        lightmap.update(self, moving_lights);
+        self.lightmap = lightmap;
+    }
+
+    // Devuelve el tipo de pared de la celda, o 0 si está vacía o fuera del mapa
+    pub fn wall_at(&self, x: f64, y: f64) -> i32 {
         let xi = x as usize;
         let yi = y as usize;
         if xi < self.width && yi < self.height {
-            self.grid[yi][xi] > 0
+            self.grid[yi][xi]
         } else {
-            false
+            0
+        }
+    }
+
+    // Textura del suelo en la celda, o 0 si no tiene o está fuera del mapa
+    pub fn floor_at(&self, x: f64, y: f64) -> i32 {
+        Self::layer_at(&self.floor, x, y)
+    }
+
+    // Textura del techo en la celda, o 0 si no tiene o está fuera del mapa
+    pub fn ceiling_at(&self, x: f64, y: f64) -> i32 {
+        Self::layer_at(&self.ceiling, x, y)
+    }
+
+    fn layer_at(layer: &[Vec<i32>], x: f64, y: f64) -> i32 {
+        if x < 0.0 || y < 0.0 {
+            return 0;
         }
+        layer
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+            .unwrap_or(0)
     }
 
-    pub fn set_wall(&mut self, x: usize, y: usize) {
+    pub fn set_wall(&mut self, x: usize, y: usize, wall: i32) {
         if x < self.width && y < self.height {
-            self.grid[y][x] = 1; // 1 representa una pared
+            self.grid[y][x] = wall;
+            if wall == DOOR {
+                self.doors.entry((x, y)).or_default();
+            } else {
+                self.doors.remove(&(x, y));
+            }
+        }
+    }
+}
+
+// Formato de los archivos de mapa:
+//
+//   # comentario
+//   width 24
+//   height 23
+//   player 12 12
+//   direction 0
+//   texture 2 src/stone_texture.png
+//   texture D src/door_texture.png
+//   enemy 18.5 15.5 18.5 20.5 15.5 20.5
+//   fog 202830 4 14 1.5
+//   side_shade 0.7
+//   ambient 0.4
+//   light 12.5 15.5 FFB060 6 1.2
+//   player_light FFFFFF 4 0.8
+//   grid
+//   111111111111111111111111
+//   1000000D0000000000000001
+//   ...
+//   floor
+//   000000000000000000000000
+//   055555555555555555555550
+//   ...
+//
+// La cabecera usa líneas `clave valor`; `direction` va en grados y es opcional.
+// `texture` asigna una imagen a un tipo de celda y puede repetirse.
+// `enemy` coloca un enemigo en `x y` seguido de sus puntos de patrulla `x y` opcionales
+// y puede repetirse.
+// `fog COLOR INICIO FIN [CURVA]` activa la niebla con un color hexadecimal RRGGBB
+// y `side_shade` fija el brillo de las caras norte/sur entre 0 y 1.
+// `light X Y COLOR RADIO INTENSIDAD` coloca una luz fija y puede repetirse;
+// `player_light COLOR RADIO INTENSIDAD` da una luz al jugador y `ambient` fija la luz
+// mínima de todas las celdas (1.0 por defecto).
+// Después de `grid` vienen exactamente `height` filas de `width` caracteres,
+// donde cada dígito es el valor de la celda (0 es espacio vacío) y `D` es una puerta.
+// Las secciones opcionales `floor` y `ceiling` tienen el mismo tamaño e indican
+// la textura del suelo y del techo de cada celda (0 usa el color plano).
+impl FromStr for Map {
+    type Err = MapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut width: Option<usize> = None;
+        let mut height: Option<usize> = None;
+        let mut spawn: Option<(f64, f64, usize)> = None;
+        let mut direction = 0.0;
+        let mut grid: Option<Vec<Vec<i32>>> = None;
+        let mut floor: Option<Vec<Vec<i32>>> = None;
+        let mut ceiling: Option<Vec<Vec<i32>>> = None;
+        let mut textures = HashMap::new();
+        let mut enemies = Vec::new();
+        let mut shading = Shading::default();
+        let mut lights = Vec::new();
+        let mut player_light = None;
+        let mut ambient = 1.0;
+
+        let mut lines = s.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        while let Some((line_no, line)) = lines.next() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let tokens = tokenize(line);
+            let (key, column) = tokens[0];
+            let args = &tokens[1..];
+
+            match key {
+                "width" => width = Some(parse_value(line_no, key, column, args)?),
+                "height" => height = Some(parse_value(line_no, key, column, args)?),
+                "direction" => {
+                    let degrees: f64 = parse_value(line_no, key, column, args)?;
+                    direction = degrees.to_radians();
+                }
+                "player" => {
+                    if args.len() != 2 {
+                        return Err(parse_error(line_no, column, "`player` expects an x and a y coordinate"));
+                    }
+                    let x = parse_token(line_no, args[0])?;
+                    let y = parse_token(line_no, args[1])?;
+                    spawn = Some((x, y, line_no));
+                }
+                "texture" => {
+                    if args.len() < 2 {
+                        return Err(parse_error(line_no, column, "`texture` expects a cell type and an image path"));
+                    }
+                    let (id_token, id_column) = args[0];
+                    let mut chars = id_token.chars();
+                    let cell = match (chars.next().and_then(parse_cell), chars.next()) {
+                        (Some(cell), None) if cell > 0 => cell,
+                        _ => {
+                            return Err(parse_error(
+                                line_no,
+                                id_column,
+                                format!("cell type `{}` must be a digit between 1 and 9 or `D`", id_token),
+                            ));
+                        }
+                    };
+                    let path = line[args[1].1 - 1..].trim_end();
+                    textures.insert(cell, path.to_string());
+                }
+                "enemy" => {
+                    if args.len() < 2 || !args.len().is_multiple_of(2) {
+                        return Err(parse_error(
+                            line_no,
+                            column,
+                            "`enemy` expects an x and a y coordinate followed by waypoint pairs",
+                        ));
+                    }
+                    let mut points = Vec::with_capacity(args.len() / 2);
+                    for pair in args.chunks(2) {
+                        let x: f64 = parse_token(line_no, pair[0])?;
+                        let y: f64 = parse_token(line_no, pair[1])?;
+                        points.push((x, y, pair[0].1));
+                    }
+                    enemies.push((points, line_no));
+                }
+                "fog" => {
+                    if args.len() != 3 && args.len() != 4 {
+                        return Err(parse_error(
+                            line_no,
+                            column,
+                            "`fog` expects a color, a start and an end distance and an optional falloff",
+                        ));
+                    }
+                    let color = parse_color(line_no, args[0])?;
+                    let start: f64 = parse_token(line_no, args[1])?;
+                    let end: f64 = parse_token(line_no, args[2])?;
+                    let falloff: f64 = match args.get(3) {
+                        Some(&token) => parse_token(line_no, token)?,
+                        None => 1.0,
+                    };
+                    if start < 0.0 || end <= start {
+                        return Err(parse_error(line_no, args[2].1, "fog end must be greater than its start"));
+                    }
+                    if falloff <= 0.0 {
+                        return Err(parse_error(line_no, args[3].1, "fog falloff must be positive"));
+                    }
+                    shading.fog = Some(Fog { color, start, end, falloff });
+                }
+                "side_shade" => {
+                    let shade: f64 = parse_value(line_no, key, column, args)?;
+                    if !(0.0..=1.0).contains(&shade) {
+                        return Err(parse_error(line_no, args[0].1, "`side_shade` must be between 0 and 1"));
+                    }
+                    shading.side_shade = shade;
+                }
+                "ambient" => {
+                    ambient = parse_value(line_no, key, column, args)?;
+                    if ambient < 0.0 {
+                        return Err(parse_error(line_no, args[0].1, "`ambient` must not be negative"));
+                    }
+                }
+                "light" => {
+                    if args.len() != 5 {
+                        return Err(parse_error(
+                            line_no,
+                            column,
+                            "`light` expects an x and a y coordinate, a color, a radius and an intensity",
+                        ));
+                    }
+                    let x = parse_token(line_no, args[0])?;
+                    let y = parse_token(line_no, args[1])?;
+                    let light = parse_light(line_no, x, y, &args[2..])?;
+                    lights.push((light, line_no, args[0].1));
+                }
+                "player_light" => {
+                    if args.len() != 3 {
+                        return Err(parse_error(
+                            line_no,
+                            column,
+                            "`player_light` expects a color, a radius and an intensity",
+                        ));
+                    }
+                    player_light = Some(parse_light(line_no, 0.0, 0.0, args)?);
+                }
+                "grid" | "floor" | "ceiling" => {
+                    let section = match key {
+                        "grid" => &mut grid,
+                        "floor" => &mut floor,
+                        _ => &mut ceiling,
+                    };
+                    if section.is_some() {
+                        return Err(parse_error(line_no, column, format!("duplicate `{}` section", key)));
+                    }
+                    let (Some(w), Some(h)) = (width, height) else {
+                        return Err(parse_error(
+                            line_no,
+                            column,
+                            format!("`width` and `height` must come before `{}`", key),
+                        ));
+                    };
+                    *section = Some(parse_grid(&mut lines, line_no, w, h)?);
+                }
+                _ => {
+                    return Err(parse_error(line_no, column, format!("unknown key `{}`", key)));
+                }
+            }
+        }
+
+        let last_line = s.lines().count().max(1);
+        let Some(grid) = grid else {
+            return Err(parse_error(last_line, 1, "missing `grid` section"));
+        };
+        let Some((spawn_x, spawn_y, spawn_line)) = spawn else {
+            return Err(parse_error(last_line, 1, "missing `player` start position"));
+        };
+
+        let mut map = Map::new(grid[0].len(), grid.len());
+        for (y, row) in grid.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                if cell > 0 {
+                    map.set_wall(x, y, cell);
+                }
+            }
+        }
+
+        if spawn_x < 0.0 || spawn_y < 0.0 || spawn_x >= map.width as f64 || spawn_y >= map.height as f64 {
+            return Err(parse_error(spawn_line, 1, "player start is outside the map"));
+        }
+        if map.is_wall(spawn_x, spawn_y) {
+            return Err(parse_error(spawn_line, 1, "player start is inside a wall"));
+        }
+        // Con la misma comprobación que al moverse, para que no empiece pegado a una pared
+        if Player::new(spawn_x, spawn_y, direction).collides_at(spawn_x, spawn_y, &map) {
+            return Err(parse_error(spawn_line, 1, "player start is too close to a wall"));
+        }
+
+        for (points, line_no) in enemies {
+            for &(x, y, column) in &points {
+                if x < 0.0 || y < 0.0 || x >= map.width as f64 || y >= map.height as f64 {
+                    return Err(parse_error(line_no, column, "enemy position is outside the map"));
+                }
+                if map.is_wall(x, y) {
+                    return Err(parse_error(line_no, column, "enemy position is inside a wall"));
+                }
+            }
+            let waypoints = points[1..].iter().map(|&(x, y, _)| (x, y)).collect();
+            map.enemies.push(Enemy::new(points[0].0, points[0].1, waypoints));
         }
+
+        for (light, line_no, column) in lights {
+            if light.x < 0.0 || light.y < 0.0 || light.x >= map.width as f64 || light.y >= map.height as f64 {
+                return Err(parse_error(line_no, column, "light position is outside the map"));
+            }
+            if map.is_wall(light.x, light.y) {
+                return Err(parse_error(line_no, column, "light position is inside a wall"));
+            }
+            map.lights.push(light);
+        }
+        map.player_light = player_light;
+        map.ambient = ambient;
+
+        if let Some(floor) = floor {
+            map.floor = floor;
+        }
+        if let Some(ceiling) = ceiling {
+            map.ceiling = ceiling;
+        }
+        map.textures = textures;
+        map.shading = shading;
+        map.spawn_x = spawn_x;
+        map.spawn_y = spawn_y;
+        map.spawn_direction = direction;
+        map.bake_lights();
+
+        Ok(map)
     }
 }
-pub fn initialize_map() -> Map {
-    let mut map = Map::new(24, 24);
-
-    let grid = vec![
-        vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 1],
-        vec![1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1],
-        vec![1, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 1, 0, 1],
-        vec![1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 1],
-        vec![1, 0, 1, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1],
-        vec![1, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1],
-        vec![1, 0, 1, 0, 1, 0, 1, 0, 1, 1, 1, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1],
-        vec![1, 0, 1, 0, 0, 0, 1, 0, 1, 0, 0, 1, 0, 1, 0, 1, 0, 1, 0, 0, 0, 1, 0, 1],
-        vec![1, 0, 1, 1, 1, 1, 1, 0, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 0, 1, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
-        vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
-    ];
-
-    for y in 0..grid.len() {
-        for x in 0..grid[y].len() {
-            if grid[y][x] == 1 {
-                map.set_wall(x, y);
+
+// Separa una línea en palabras junto con su columna (empezando en 1)
+fn tokenize(line: &str) -> Vec<(&str, usize)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+
+    for (i, ch) in line.char_indices() {
+        if ch.is_whitespace() {
+            if let Some(s) = start.take() {
+                tokens.push((&line[s..i], s + 1));
             }
+        } else if start.is_none() {
+            start = Some(i);
         }
     }
+    if let Some(s) = start {
+        tokens.push((&line[s..], s + 1));
+    }
+
+    tokens
+}
+
+fn parse_cell(ch: char) -> Option<i32> {
+    match ch {
+        'D' => Some(DOOR),
+        _ => ch.to_digit(10).map(|value| value as i32),
+    }
+}
+
+fn parse_token<T: FromStr>(line: usize, (token, column): (&str, usize)) -> Result<T, MapError> {
+    token
+        .parse()
+        .map_err(|_| parse_error(line, column, format!("invalid number `{}`", token)))
+}
+
+// Color en hexadecimal `RRGGBB`, con `#` opcional
+fn parse_color(line: usize, (token, column): (&str, usize)) -> Result<u32, MapError> {
+    let hex = token.strip_prefix('#').unwrap_or(token);
+    match u32::from_str_radix(hex, 16) {
+        Ok(color) if hex.len() == 6 && hex.chars().all(|ch| ch.is_ascii_hexdigit()) => Ok(color),
+        _ => Err(parse_error(line, column, format!("invalid color `{}`, expected RRGGBB", token))),
+    }
+}
 
-    map
+// Color, radio e intensidad de una luz
+fn parse_light(line: usize, x: f64, y: f64, args: &[(&str, usize)]) -> Result<Light, MapError> {
+    let color = parse_color(line, args[0])?;
+    let radius: f64 = parse_token(line, args[1])?;
+    let intensity: f64 = parse_token(line, args[2])?;
+    if radius <= 0.0 {
+        return Err(parse_error(line, args[1].1, "light radius must be positive"));
+    }
+    if intensity < 0.0 {
+        return Err(parse_error(line, args[2].1, "light intensity must not be negative"));
+    }
+    Ok(Light::new(x, y, color, radius, intensity))
+}
+
+fn parse_value<T: FromStr>(line: usize, key: &str, column: usize, args: &[(&str, usize)]) -> Result<T, MapError> {
+    match args {
+        [value] => parse_token(line, *value),
+        _ => Err(parse_error(line, column, format!("`{}` expects exactly one value", key))),
+    }
+}
+
+fn parse_grid<'a, I>(lines: &mut I, header_line: usize, width: usize, height: usize) -> Result<Vec<Vec<i32>>, MapError>
+where
+    I: Iterator<Item = (usize, &'a str)>,
+{
+    if width == 0 || height == 0 {
+        return Err(parse_error(header_line, 1, "map must be at least 1x1"));
+    }
+
+    let mut grid = Vec::with_capacity(height);
+    let mut last_line = header_line;
+
+    for _ in 0..height {
+        let Some((line_no, line)) = lines.next() else {
+            return Err(parse_error(
+                last_line + 1,
+                1,
+                format!("expected {} grid rows, found {}", height, grid.len()),
+            ));
+        };
+        last_line = line_no;
+
+        let row_text = line.trim_end();
+        let mut row = Vec::with_capacity(width);
+        for (i, ch) in row_text.chars().enumerate() {
+            let Some(value) = parse_cell(ch) else {
+                return Err(parse_error(line_no, i + 1, format!("invalid cell `{}`", ch)));
+            };
+            row.push(value);
+        }
+
+        if row.len() != width {
+            return Err(parse_error(
+                line_no,
+                row.len().min(width) + 1,
+                format!("expected {} cells, found {}", width, row.len()),
+            ));
+        }
+        grid.push(row);
+    }
+
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "grid\n1111\n1001\n1111\n";
+
+    fn with_player(player: &str) -> String {
+        format!("width 4\nheight 3\nplayer {}\n{}", player, GRID)
+    }
+
+    // Línea, columna y mensaje del error al leer `text`
+    fn error_of(text: &str) -> (usize, usize, String) {
+        match text.parse::<Map>() {
+            Err(MapError::Parse { line, column, message }) => (line, column, message),
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn bad_grid_character_reports_its_line_and_column() {
+        let text = "width 4\nheight 3\nplayer 1.5 1.5\ngrid\n1111\n10x1\n1111\n";
+        assert_eq!(error_of(text), (6, 3, "invalid cell `x`".to_string()));
+    }
+
+    #[test]
+    fn grid_needs_width_and_height() {
+        let text = format!("height 3\nplayer 1.5 1.5\n{}", GRID);
+        assert_eq!(error_of(&text), (3, 1, "`width` and `height` must come before `grid`".to_string()));
+    }
+
+    #[test]
+    fn short_grid_row_is_reported() {
+        let text = "width 4\nheight 3\nplayer 1.5 1.5\ngrid\n1111\n101\n1111\n";
+        assert_eq!(error_of(text), (6, 4, "expected 4 cells, found 3".to_string()));
+    }
+
+    #[test]
+    fn unknown_key_is_reported() {
+        let text = format!("width 4\nheight 3\n  speed 3\nplayer 1.5 1.5\n{}", GRID);
+        assert_eq!(error_of(&text), (3, 3, "unknown key `speed`".to_string()));
+    }
+
+    #[test]
+    fn spawn_must_leave_room_for_the_player() {
+        assert!(with_player("1.5 1.5").parse::<Map>().is_ok());
+        assert!(with_player("2 1.5").parse::<Map>().is_ok());
+        assert_eq!(error_of(&with_player("0.5 0.5")), (3, 1, "player start is inside a wall".to_string()));
+        assert_eq!(error_of(&with_player("1.1 1.5")), (3, 1, "player start is too close to a wall".to_string()));
+    }
 }
@@ -0,0 +1,441 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::map::Map;
+
+const MIN_SIZE: usize = 5;
+const ROOM_MIN: usize = 1; // Tamaño de las salas en celdas del laberinto
+const ROOM_MAX: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MazeAlgorithm {
+    RecursiveBacktracker,
+    Prim,
+    Kruskal,
+}
+
+impl FromStr for MazeAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "backtracker" => Ok(MazeAlgorithm::RecursiveBacktracker),
+            "prim" => Ok(MazeAlgorithm::Prim),
+            "kruskal" => Ok(MazeAlgorithm::Kruskal),
+            _ => Err(format!("unknown maze algorithm `{}` (expected backtracker, prim or kruskal)", s)),
+        }
+    }
+}
+
+impl fmt::Display for MazeAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MazeAlgorithm::RecursiveBacktracker => "backtracker",
+            MazeAlgorithm::Prim => "prim",
+            MazeAlgorithm::Kruskal => "kruskal",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MazeOptions {
+    pub width: usize, // Tamaño del mapa resultante, incluido el borde (se redondea a impar)
+    pub height: usize,
+    pub seed: u64,
+    pub algorithm: MazeAlgorithm,
+    pub braid: f64,   // Fracción de callejones sin salida que se abren (0.0 a 1.0)
+    pub rooms: usize, // Número de salas abiertas que se tallan
+}
+
+impl MazeOptions {
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Self {
+            width,
+            height,
+            seed,
+            algorithm: MazeAlgorithm::RecursiveBacktracker,
+            braid: 0.0,
+            rooms: 0,
+        }
+    }
+}
+
+// El laberinto se construye sobre celdas en coordenadas impares del mapa; las
+// coordenadas pares entre ellas son las paredes que se van abriendo.
+struct Carver {
+    map: Map,
+    cells_x: usize,
+    cells_y: usize,
+}
+
+impl Carver {
+    fn new(width: usize, height: usize) -> Self {
+        let mut map = Map::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                map.set_wall(x, y, 1);
+            }
+        }
+        Self {
+            map,
+            cells_x: (width - 1) / 2,
+            cells_y: (height - 1) / 2,
+        }
+    }
+
+    fn index(&self, (cx, cy): (usize, usize)) -> usize {
+        cy * self.cells_x + cx
+    }
+
+    fn cell_count(&self) -> usize {
+        self.cells_x * self.cells_y
+    }
+
+    fn open(&mut self, x: usize, y: usize) {
+        self.map.set_wall(x, y, 0);
+    }
+
+    fn is_open(&self, x: usize, y: usize) -> bool {
+        self.map.grid[y][x] == 0
+    }
+
+    fn open_cell(&mut self, (cx, cy): (usize, usize)) {
+        self.open(2 * cx + 1, 2 * cy + 1);
+    }
+
+    // Abre la pared entre dos celdas vecinas y ambas celdas
+    fn connect(&mut self, a: (usize, usize), b: (usize, usize)) {
+        self.open_cell(a);
+        self.open_cell(b);
+        self.open(a.0 + b.0 + 1, a.1 + b.1 + 1);
+    }
+
+    fn neighbors(&self, (cx, cy): (usize, usize)) -> Vec<(usize, usize)> {
+        let mut neighbors = Vec::with_capacity(4);
+        if cx > 0 {
+            neighbors.push((cx - 1, cy));
+        }
+        if cx + 1 < self.cells_x {
+            neighbors.push((cx + 1, cy));
+        }
+        if cy > 0 {
+            neighbors.push((cx, cy - 1));
+        }
+        if cy + 1 < self.cells_y {
+            neighbors.push((cx, cy + 1));
+        }
+        neighbors
+    }
+
+    fn is_connected(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        self.is_open(a.0 + b.0 + 1, a.1 + b.1 + 1)
+    }
+
+    fn recursive_backtracker(&mut self, rng: &mut ChaCha8Rng) {
+        let mut visited = vec![false; self.cell_count()];
+        let start = (rng.gen_range(0..self.cells_x), rng.gen_range(0..self.cells_y));
+        let mut stack = vec![start];
+        visited[self.index(start)] = true;
+        self.open_cell(start);
+
+        // Se usa una pila explícita en vez de recursión para no desbordar en mapas grandes
+        while let Some(&current) = stack.last() {
+            let unvisited: Vec<_> = self
+                .neighbors(current)
+                .into_iter()
+                .filter(|&cell| !visited[self.index(cell)])
+                .collect();
+
+            match unvisited.choose(rng) {
+                Some(&next) => {
+                    visited[self.index(next)] = true;
+                    self.connect(current, next);
+                    stack.push(next);
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    fn prim(&mut self, rng: &mut ChaCha8Rng) {
+        let mut in_maze = vec![false; self.cell_count()];
+        let mut in_frontier = vec![false; self.cell_count()];
+        let mut frontier = Vec::new();
+
+        let start = (rng.gen_range(0..self.cells_x), rng.gen_range(0..self.cells_y));
+        in_maze[self.index(start)] = true;
+        self.open_cell(start);
+        for cell in self.neighbors(start) {
+            in_frontier[self.index(cell)] = true;
+            frontier.push(cell);
+        }
+
+        while !frontier.is_empty() {
+            let cell = frontier.swap_remove(rng.gen_range(0..frontier.len()));
+
+            let neighbors = self.neighbors(cell);
+            let joined: Vec<_> = neighbors.iter().copied().filter(|&n| in_maze[self.index(n)]).collect();
+            if let Some(&target) = joined.choose(rng) {
+                self.connect(cell, target);
+            }
+            in_maze[self.index(cell)] = true;
+
+            for next in neighbors {
+                let index = self.index(next);
+                if !in_maze[index] && !in_frontier[index] {
+                    in_frontier[index] = true;
+                    frontier.push(next);
+                }
+            }
+        }
+    }
+
+    fn kruskal(&mut self, rng: &mut ChaCha8Rng) {
+        let mut edges = Vec::new();
+        for cy in 0..self.cells_y {
+            for cx in 0..self.cells_x {
+                if cx + 1 < self.cells_x {
+                    edges.push(((cx, cy), (cx + 1, cy)));
+                }
+                if cy + 1 < self.cells_y {
+                    edges.push(((cx, cy), (cx, cy + 1)));
+                }
+            }
+        }
+        edges.shuffle(rng);
+
+        let mut sets = DisjointSet::new(self.cell_count());
+        for cy in 0..self.cells_y {
+            for cx in 0..self.cells_x {
+                self.open_cell((cx, cy));
+            }
+        }
+        for (a, b) in edges {
+            if sets.union(self.index(a), self.index(b)) {
+                self.connect(a, b);
+            }
+        }
+    }
+
+    // Abre una pared en los callejones sin salida, con probabilidad `braid` cada uno
+    fn braid(&mut self, rng: &mut ChaCha8Rng, braid: f64) {
+        for cy in 0..self.cells_y {
+            for cx in 0..self.cells_x {
+                let cell = (cx, cy);
+                let neighbors = self.neighbors(cell);
+                let open = neighbors.iter().filter(|&&n| self.is_connected(cell, n)).count();
+                if open != 1 || !rng.gen_bool(braid) {
+                    continue;
+                }
+
+                // Preferir unir con otro callejón para eliminar dos de una vez
+                let closed: Vec<_> = neighbors.into_iter().filter(|&n| !self.is_connected(cell, n)).collect();
+                let dead_ends: Vec<_> = closed
+                    .iter()
+                    .copied()
+                    .filter(|&n| self.neighbors(n).iter().filter(|&&m| self.is_connected(n, m)).count() == 1)
+                    .collect();
+                let candidates = if dead_ends.is_empty() { &closed } else { &dead_ends };
+                if let Some(&target) = candidates.choose(rng) {
+                    self.connect(cell, target);
+                }
+            }
+        }
+    }
+
+    // Talla salas rectangulares abiertas sobre el laberinto
+    fn carve_rooms(&mut self, rng: &mut ChaCha8Rng, rooms: usize) {
+        for _ in 0..rooms {
+            let room_w = rng.gen_range(ROOM_MIN..=ROOM_MAX).min(self.cells_x);
+            let room_h = rng.gen_range(ROOM_MIN..=ROOM_MAX).min(self.cells_y);
+            let cx = rng.gen_range(0..=self.cells_x - room_w);
+            let cy = rng.gen_range(0..=self.cells_y - room_h);
+
+            for y in (2 * cy + 1)..(2 * (cy + room_h)) {
+                for x in (2 * cx + 1)..(2 * (cx + room_w)) {
+                    self.open(x, y);
+                }
+            }
+        }
+    }
+}
+
+struct DisjointSet {
+    parent: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        Self { parent: (0..size).collect() }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    // Une los conjuntos de `a` y `b`; devuelve false si ya estaban unidos
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let root_a = self.find(a);
+        let root_b = self.find(b);
+        if root_a == root_b {
+            return false;
+        }
+        self.parent[root_a] = root_b;
+        true
+    }
+}
+
+// Genera un laberinto rodeado de paredes; la misma semilla y opciones dan siempre el mismo mapa.
+// Se usa ChaCha8 en vez de StdRng porque StdRng puede cambiar de algoritmo entre versiones de rand.
+pub fn generate(options: &MazeOptions) -> Map {
+    // Las celdas van en coordenadas impares, así que los tamaños pares se suben al impar
+    // siguiente para que el borde derecho e inferior no tengan dos paredes de grosor
+    let width = options.width.max(MIN_SIZE) | 1;
+    let height = options.height.max(MIN_SIZE) | 1;
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+
+    let mut carver = Carver::new(width, height);
+    match options.algorithm {
+        MazeAlgorithm::RecursiveBacktracker => carver.recursive_backtracker(&mut rng),
+        MazeAlgorithm::Prim => carver.prim(&mut rng),
+        MazeAlgorithm::Kruskal => carver.kruskal(&mut rng),
+    }
+    if options.braid > 0.0 {
+        carver.braid(&mut rng, options.braid.min(1.0));
+    }
+    carver.carve_rooms(&mut rng, options.rooms);
+
+    // El jugador empieza en la primera celda, mirando hacia un pasillo abierto
+    let mut map = carver.map;
+    map.spawn_x = 1.5;
+    map.spawn_y = 1.5;
+    map.spawn_direction = if map.is_wall(2.5, 1.5) {
+        std::f64::consts::FRAC_PI_2
+    } else {
+        0.0
+    };
+    map.bake_lights();
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lighting::Light;
+
+    const ALGORITHMS: [MazeAlgorithm; 3] =
+        [MazeAlgorithm::RecursiveBacktracker, MazeAlgorithm::Prim, MazeAlgorithm::Kruskal];
+
+    fn options(algorithm: MazeAlgorithm, seed: u64) -> MazeOptions {
+        let mut options = MazeOptions::new(11, 9, seed);
+        options.algorithm = algorithm;
+        options.braid = 0.3;
+        options.rooms = 1;
+        options
+    }
+
+    fn grid(map: &Map) -> String {
+        map.grid
+            .iter()
+            .map(|row| row.iter().map(|&cell| if cell == 0 { '.' } else { '#' }).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_maze() {
+        for algorithm in ALGORITHMS {
+            for seed in [0, 1, 12345] {
+                let a = generate(&options(algorithm, seed));
+                let b = generate(&options(algorithm, seed));
+                assert_eq!(a.grid, b.grid, "{} with seed {}", algorithm, seed);
+                assert_eq!((a.spawn_x, a.spawn_y, a.spawn_direction), (b.spawn_x, b.spawn_y, b.spawn_direction));
+            }
+        }
+    }
+
+    // Laberintos fijados para detectar si el generador o el RNG cambian entre versiones
+    #[test]
+    fn seed_gives_a_known_maze() {
+        let expected = [
+            (
+                MazeAlgorithm::RecursiveBacktracker,
+                [
+                    "###########",
+                    "#.....#...#",
+                    "#.###.###.#",
+                    "#...#.....#",
+                    "#.#.#.###.#",
+                    "#.#...#...#",
+                    "#.###.#.#.#",
+                    "#.........#",
+                    "###########",
+                ],
+            ),
+            (
+                MazeAlgorithm::Prim,
+                [
+                    "###########",
+                    "#.....#.#.#",
+                    "#.#.###.#.#",
+                    "#.........#",
+                    "#.#.#.#.###",
+                    "#.#.#.....#",
+                    "#.###.#.#.#",
+                    "#.#...#...#",
+                    "###########",
+                ],
+            ),
+            (
+                MazeAlgorithm::Kruskal,
+                [
+                    "###########",
+                    "#.........#",
+                    "###.#####.#",
+                    "#.........#",
+                    "#.#...###.#",
+                    "#.#.....#.#",
+                    "#.###.#.#.#",
+                    "#...#.....#",
+                    "###########",
+                ],
+            ),
+        ];
+        for (algorithm, rows) in expected {
+            assert_eq!(grid(&generate(&options(algorithm, 7))), rows.join("\n"), "{}", algorithm);
+        }
+    }
+
+    #[test]
+    fn even_sizes_are_rounded_up_to_odd() {
+        for algorithm in ALGORITHMS {
+            let mut options = options(algorithm, 2);
+            (options.width, options.height) = (10, 8);
+            let map = generate(&options);
+            assert_eq!((map.width, map.height), (11, 9));
+            // Ninguna fila ni columna junto al borde queda cerrada entera
+            assert!((1..map.height - 1).any(|y| map.grid[y][map.width - 2] == 0));
+            assert!((1..map.width - 1).any(|x| map.grid[map.height - 2][x] == 0));
+        }
+    }
+
+    // Sin hornear, sumar una luz móvil (como la del jugador) se salía del mapa de luz
+    #[test]
+    fn generated_maps_have_baked_lights() {
+        let mut map = generate(&options(MazeAlgorithm::Prim, 3));
+        let light = Light::new(map.spawn_x, map.spawn_y, 0xFFFFFF, 4.0, 1.0);
+        map.update_lights(&[light]);
+        assert_eq!(map.lightmap.sample(&map, map.spawn_x, map.spawn_y), [1.0; 3]);
+    }
+}
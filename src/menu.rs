@@ -0,0 +1,98 @@
+use crate::framebuffer::Framebuffer;
+use crate::input::{Action, Input};
+use crate::text::{draw_text_styled, measure_text, Align, TextStyle};
+
+const TITLE_COLOR: u32 = 0xFFFFFF;
+const ITEM_COLOR: u32 = 0xA0A0A0;
+const SELECTED_COLOR: u32 = 0xFFD040;
+const SHADOW_COLOR: u32 = 0x000000;
+
+// Lo que ha hecho el jugador en un menú durante este fotograma
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuEvent {
+    None,
+    Activate, // Confirm sobre la opción seleccionada
+    Decrease, // Izquierda sobre la opción seleccionada
+    Increase, // Derecha sobre la opción seleccionada
+    Back,     // Pause: cerrar el menú
+}
+
+// Menú vertical manejado con el teclado. Solo guarda la opción seleccionada; los textos de
+// las opciones se pasan al dibujarlo, para que puedan mostrar valores que cambian.
+pub struct Menu {
+    pub selected: usize,
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        Self { selected: 0 }
+    }
+
+    // Mueve la selección con las acciones del menú (dando la vuelta en los extremos) y
+    // devuelve lo que haya que hacer con la opción seleccionada
+    pub fn update(&mut self, input: &Input, count: usize) -> MenuEvent {
+        if count == 0 {
+            return MenuEvent::None;
+        }
+        if input.was_pressed(Action::MenuUp) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if input.was_pressed(Action::MenuDown) {
+            self.selected = (self.selected + 1) % count;
+        }
+        self.selected = self.selected.min(count - 1);
+
+        if input.was_pressed(Action::Confirm) {
+            MenuEvent::Activate
+        } else if input.was_pressed(Action::MenuLeft) {
+            MenuEvent::Decrease
+        } else if input.was_pressed(Action::MenuRight) {
+            MenuEvent::Increase
+        } else if input.was_pressed(Action::Pause) {
+            MenuEvent::Back
+        } else {
+            MenuEvent::None
+        }
+    }
+
+    // Dibuja el título y las opciones centrados en el framebuffer, marcando la seleccionada
+    pub fn draw(&self, framebuffer: &mut Framebuffer, title: &str, items: &[String]) {
+        let mut title_style = TextStyle::new(TITLE_COLOR, 4);
+        title_style.align = Align::Center;
+        title_style.shadow = Some(SHADOW_COLOR);
+
+        let mut item_style = TextStyle::new(ITEM_COLOR, 2);
+        item_style.align = Align::Center;
+        item_style.shadow = Some(SHADOW_COLOR);
+
+        let title_height = measure_text(title, title_style.scale).1 + 2 * item_style.line_height();
+        let height = title_height + items.len() * item_style.line_height();
+        let center = framebuffer.width / 2;
+        let top = framebuffer.height.saturating_sub(height) / 2;
+
+        draw_text_styled(framebuffer, center, top, title, &title_style);
+        for (i, item) in items.iter().enumerate() {
+            let y = top + title_height + i * item_style.line_height();
+            if i == self.selected {
+                let mut selected_style = item_style;
+                selected_style.color = SELECTED_COLOR;
+                draw_text_styled(framebuffer, center, y, &format!("> {} <", item), &selected_style);
+            } else {
+                draw_text_styled(framebuffer, center, y, item, &item_style);
+            }
+        }
+    }
+}
+
+impl Default for Menu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Oscurece lo que haya en el framebuffer para que el menú se lea encima del juego
+pub fn dim(framebuffer: &mut Framebuffer) {
+    for pixel in framebuffer.buffer.iter_mut() {
+        *pixel = (*pixel >> 2) & 0x3F3F3F;
+    }
+}
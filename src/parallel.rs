@@ -0,0 +1,47 @@
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+// Hilos con los que se reparte el renderizado. Con un solo hilo todo se hace en serie
+// en el hilo que llama, sin crear ningún hilo extra.
+pub struct RenderPool {
+    pool: Option<ThreadPool>,
+}
+
+impl RenderPool {
+    // `threads` 0 usa un hilo por núcleo
+    pub fn new(threads: usize) -> Result<Self, ThreadPoolBuildError> {
+        if threads == 1 {
+            return Ok(Self::serial());
+        }
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+        Ok(Self { pool: Some(pool) })
+    }
+
+    pub fn serial() -> Self {
+        Self { pool: None }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.pool.as_ref().map_or(1, ThreadPool::current_num_threads)
+    }
+
+    // Aplica `f` a cada trozo de `chunk_size` elementos, con su índice, y devuelve los
+    // resultados en orden. Cada trozo solo se toca desde un hilo, así que el resultado
+    // es el mismo en serie y en paralelo.
+    pub fn map_chunks<T, R, F>(&self, data: &mut [T], chunk_size: usize, f: F) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(usize, &mut [T]) -> R + Sync + Send,
+    {
+        match &self.pool {
+            Some(pool) => pool.install(|| {
+                data.par_chunks_mut(chunk_size)
+                    .enumerate()
+                    .map(|(i, chunk)| f(i, chunk))
+                    .collect()
+            }),
+            None => data.chunks_mut(chunk_size).enumerate().map(|(i, chunk)| f(i, chunk)).collect(),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::map::Map;
+
+// Nodo abierto de A*, ordenado para que el montículo saque primero el de menor coste estimado
+#[derive(PartialEq, Eq)]
+struct Node {
+    estimate: usize,
+    cost: usize,
+    index: usize,
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .cmp(&self.estimate)
+            .then_with(|| other.cost.cmp(&self.cost))
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub fn is_walkable(map: &Map, x: usize, y: usize) -> bool {
+    x < map.width && y < map.height && !map.is_wall(x as f64 + 0.5, y as f64 + 0.5)
+}
+
+// Busca con A* el camino más corto entre dos celdas moviéndose en las cuatro direcciones.
+// Devuelve las celdas a recorrer sin incluir la de partida, o None si no hay camino.
+// La celda de partida no tiene que ser transitable, por si una puerta se cerró encima.
+pub fn find_path(map: &Map, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+    if start.0 >= map.width || start.1 >= map.height || !is_walkable(map, goal.0, goal.1) {
+        return None;
+    }
+
+    let width = map.width;
+    let heuristic = |index: usize| (index % width).abs_diff(goal.0) + (index / width).abs_diff(goal.1);
+
+    let start_index = start.1 * width + start.0;
+    let goal_index = goal.1 * width + goal.0;
+
+    let mut best_cost = vec![usize::MAX; width * map.height];
+    let mut came_from = vec![usize::MAX; width * map.height];
+    let mut open = BinaryHeap::new();
+
+    best_cost[start_index] = 0;
+    open.push(Node { estimate: heuristic(start_index), cost: 0, index: start_index });
+
+    while let Some(Node { cost, index, .. }) = open.pop() {
+        if index == goal_index {
+            break;
+        }
+        if cost > best_cost[index] {
+            continue;
+        }
+
+        let (x, y) = (index % width, index / width);
+        let neighbors = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbors {
+            if !is_walkable(map, nx, ny) {
+                continue;
+            }
+            let next = ny * width + nx;
+            let next_cost = cost + 1;
+            if next_cost < best_cost[next] {
+                best_cost[next] = next_cost;
+                came_from[next] = index;
+                open.push(Node { estimate: next_cost + heuristic(next), cost: next_cost, index: next });
+            }
+        }
+    }
+
+    if best_cost[goal_index] == usize::MAX {
+        return None;
+    }
+
+    // Reconstruir el camino desde la meta hacia atrás
+    let mut path = Vec::new();
+    let mut current = goal_index;
+    while current != start_index {
+        path.push((current % width, current / width));
+        current = came_from[current];
+    }
+    path.reverse();
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::door::DoorState;
+
+    // Dos salas separadas por una puerta en (2, 1) y una celda cerrada por paredes en (5, 1)
+    const MAP: &str = "width 7\nheight 3\nplayer 1.5 1.5\ngrid\n1111111\n10D0101\n1111111\n";
+
+    #[test]
+    fn unreachable_goal_has_no_path() {
+        let map: Map = MAP.parse().unwrap();
+        assert_eq!(find_path(&map, (1, 1), (5, 1)), None);
+        assert_eq!(find_path(&map, (1, 1), (4, 1)), None);
+    }
+
+    #[test]
+    fn start_on_the_goal_gives_an_empty_path() {
+        let map: Map = MAP.parse().unwrap();
+        assert_eq!(find_path(&map, (1, 1), (1, 1)), Some(Vec::new()));
+    }
+
+    #[test]
+    fn path_goes_through_an_open_door() {
+        let mut map: Map = MAP.parse().unwrap();
+        assert_eq!(find_path(&map, (1, 1), (3, 1)), None);
+
+        let door = map.doors.get_mut(&(2, 1)).unwrap();
+        door.open_amount = 1.0;
+        door.state = DoorState::Open;
+        assert_eq!(find_path(&map, (1, 1), (3, 1)), Some(vec![(2, 1), (3, 1)]));
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::map::{Map, DOOR};
+
+// Posiciones (centros de celda) elegidas para la llave y la meta
+pub struct Placement {
+    pub key: (f64, f64),
+    pub goal: (f64, f64),
+}
+
+#[derive(Debug)]
+pub enum PlacementError {
+    StartBlocked, // El jugador empieza dentro de una pared
+    NoValidPlacement { min_distance: usize }, // No hay celdas alcanzables lo bastante separadas
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementError::StartBlocked => write!(f, "the player start is not a walkable cell"),
+            PlacementError::NoValidPlacement { min_distance } => write!(
+                f,
+                "no reachable cells are at least {} steps apart from the start and each other",
+                min_distance
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlacementError {}
+
+// Las puertas cuentan como transitables porque el jugador puede abrirlas
+fn is_walkable(map: &Map, x: usize, y: usize) -> bool {
+    let cell = map.grid[y][x];
+    cell == 0 || cell == DOOR
+}
+
+// Recorre en anchura las celdas a menos de `limit` pasos de `start` y anota su distancia en
+// `distances`, que tiene que llegar vacía. Devuelve las celdas visitadas, para poder vaciarla.
+fn explore(map: &Map, start: (usize, usize), limit: usize, distances: &mut [Option<usize>]) -> Vec<usize> {
+    let mut visited = Vec::new();
+    if limit == 0 {
+        return visited;
+    }
+    let mut queue = VecDeque::new();
+    let start_index = start.1 * map.width + start.0;
+    distances[start_index] = Some(0);
+    visited.push(start_index);
+    queue.push_back(start);
+
+    while let Some((x, y)) = queue.pop_front() {
+        let distance = distances[y * map.width + x].unwrap_or(0);
+        if distance + 1 >= limit {
+            continue;
+        }
+        let neighbors = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbors {
+            if nx >= map.width || ny >= map.height || !is_walkable(map, nx, ny) {
+                continue;
+            }
+            let index = ny * map.width + nx;
+            if distances[index].is_none() {
+                distances[index] = Some(distance + 1);
+                visited.push(index);
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+
+    visited
+}
+
+fn cell_center(map: &Map, index: usize) -> (f64, f64) {
+    ((index % map.width) as f64 + 0.5, (index / map.width) as f64 + 0.5)
+}
+
+// Coloca la llave y la meta en celdas alcanzables desde `start`, separadas entre sí
+// y del inicio por al menos `min_distance` pasos de camino
+pub fn place_key_and_goal<R: Rng>(
+    map: &Map,
+    start: (f64, f64),
+    min_distance: usize,
+    rng: &mut R,
+) -> Result<Placement, PlacementError> {
+    let (start_x, start_y) = (start.0.floor(), start.1.floor());
+    if start_x < 0.0 || start_y < 0.0 {
+        return Err(PlacementError::StartBlocked);
+    }
+    let start = (start_x as usize, start_y as usize);
+    if start.0 >= map.width || start.1 >= map.height || !is_walkable(map, start.0, start.1) {
+        return Err(PlacementError::StartBlocked);
+    }
+
+    // Las puertas no sirven para colocar objetos, solo para pasar
+    let mut from_start = vec![None; map.width * map.height];
+    explore(map, start, usize::MAX, &mut from_start);
+    let candidates: Vec<usize> = (0..from_start.len())
+        .filter(|&i| from_start[i].is_some_and(|d| d >= min_distance) && map.grid[i / map.width][i % map.width] == 0)
+        .collect();
+
+    let mut key_candidates = candidates.clone();
+    key_candidates.shuffle(rng);
+
+    // Todas las candidatas son alcanzables desde cualquier llave, así que basta con recorrer
+    // las celdas demasiado cercanas a cada llave y no el mapa entero
+    let mut near_key = vec![None; map.width * map.height];
+    for key in key_candidates {
+        let near = explore(map, (key % map.width, key / map.width), min_distance, &mut near_key);
+        let too_close = near.iter().filter(|&&i| candidates.binary_search(&i).is_ok()).count();
+        if too_close < candidates.len() {
+            let goal_candidates: Vec<usize> = candidates.iter().copied().filter(|&i| near_key[i].is_none()).collect();
+            if let Some(&goal) = goal_candidates.choose(rng) {
+                return Ok(Placement {
+                    key: cell_center(map, key),
+                    goal: cell_center(map, goal),
+                });
+            }
+        }
+        for i in near {
+            near_key[i] = None;
+        }
+    }
+
+    Err(PlacementError::NoValidPlacement { min_distance })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    // Un anillo de pasillos con dos puertas y, debajo, otro pasillo al que no se puede llegar
+    const MAP: &str = "width 12\nheight 7\nplayer 1.5 1.5\ngrid\n\
+                       111111111111\n\
+                       1000D0000001\n\
+                       1011111111D1\n\
+                       100000000001\n\
+                       111111111111\n\
+                       100000000001\n\
+                       111111111111\n";
+    const MIN_DISTANCE: usize = 5;
+
+    fn cell(position: (f64, f64)) -> (usize, usize) {
+        (position.0 as usize, position.1 as usize)
+    }
+
+    fn distances_from(map: &Map, start: (usize, usize)) -> Vec<Option<usize>> {
+        let mut distances = vec![None; map.width * map.height];
+        explore(map, start, usize::MAX, &mut distances);
+        distances
+    }
+
+    fn placements() -> Vec<(Map, Placement)> {
+        (0..50)
+            .map(|seed| {
+                let map: Map = MAP.parse().unwrap();
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                let placement = place_key_and_goal(&map, (map.spawn_x, map.spawn_y), MIN_DISTANCE, &mut rng).unwrap();
+                (map, placement)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unreachable_cells_are_never_chosen() {
+        for (map, placement) in placements() {
+            let from_start = distances_from(&map, (1, 1));
+            for (x, y) in [cell(placement.key), cell(placement.goal)] {
+                assert!(from_start[y * map.width + x].is_some(), "({}, {}) is not reachable", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn items_keep_the_minimum_distance() {
+        for (map, placement) in placements() {
+            let (key, goal) = (cell(placement.key), cell(placement.goal));
+            let from_start = distances_from(&map, (1, 1));
+            let from_key = distances_from(&map, key);
+            assert!(from_start[key.1 * map.width + key.0].unwrap() >= MIN_DISTANCE);
+            assert!(from_start[goal.1 * map.width + goal.0].unwrap() >= MIN_DISTANCE);
+            assert!(from_key[goal.1 * map.width + goal.0].unwrap() >= MIN_DISTANCE);
+        }
+    }
+
+    #[test]
+    fn items_are_never_placed_on_doors() {
+        for (map, placement) in placements() {
+            for (x, y) in [cell(placement.key), cell(placement.goal)] {
+                assert_ne!(map.grid[y][x], DOOR);
+            }
+        }
+    }
+
+    #[test]
+    fn small_map_has_no_valid_placement() {
+        let map: Map = MAP.parse().unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let result = place_key_and_goal(&map, (map.spawn_x, map.spawn_y), 12, &mut rng);
+        assert!(matches!(result, Err(PlacementError::NoValidPlacement { min_distance: 12 })));
+    }
+}
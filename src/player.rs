@@ -1,8 +1,10 @@
-use std::{fs::File, io::BufReader};
+use crate::map::Map;
 
-use rodio::{Decoder, Sink};
+const USE_DISTANCE: f64 = 1.0; // Alcance para accionar puertas
+const MOVE_SPEED: f64 = 3.0; // Unidades del mapa por segundo
+const TURN_SPEED: f64 = 1.8; // Radianes por segundo
+const RADIUS: f64 = 0.25; // Distancia mínima a la que se queda de las paredes
 
-use crate::map::Map;
 pub struct Player {
     pub x: f64,
     pub y: f64,
@@ -10,6 +12,9 @@ pub struct Player {
     pub fov: f64,       // Campo de visión
     pub plane_x: f64,   // Plano de la cámara en el eje X
     pub plane_y: f64,   // Plano de la cámara en el eje Y
+    pub move_speed: f64, // Velocidad al caminar, en unidades por segundo
+    pub turn_speed: f64, // Velocidad de giro, en radianes por segundo
+    pub radius: f64,     // Radio de colisión con las paredes
 }
 
 impl Player {
@@ -24,50 +29,73 @@ impl Player {
             fov,
             plane_x,
             plane_y,
+            move_speed: MOVE_SPEED,
+            turn_speed: TURN_SPEED,
+            radius: RADIUS,
         }
     }
 
-    pub fn move_forward(&mut self, distance: f64, map: &Map, walk_sink: &Sink) {
-        let new_x = self.x + self.direction.cos() * distance;
-        let new_y = self.y + self.direction.sin() * distance;
+    pub fn move_forward(&mut self, distance: f64, map: &Map) -> bool {
+        let dx = self.direction.cos() * distance;
+        let dy = self.direction.sin() * distance;
+        self.move_by(dx, dy, map)
+    }
 
-        if !map.is_wall(new_x, self.y) {
-            self.x = new_x;
-            self.play_walk_sound(walk_sink);
-        }
+    pub fn move_backward(&mut self, distance: f64, map: &Map) -> bool {
+        let dx = -self.direction.cos() * distance;
+        let dy = -self.direction.sin() * distance;
+        self.move_by(dx, dy, map)
+    }
 
-        if !map.is_wall(self.x, new_y) {
-            self.y = new_y;
-            self.play_walk_sound(walk_sink);
-        }
+    // Desplazamiento lateral, perpendicular a la dirección de la vista
+    pub fn strafe_left(&mut self, distance: f64, map: &Map) -> bool {
+        let dx = self.direction.sin() * distance;
+        let dy = -self.direction.cos() * distance;
+        self.move_by(dx, dy, map)
     }
 
-    pub fn move_backward(&mut self, distance: f64, map: &Map, walk_sink: &Sink) {
-        let new_x = self.x - self.direction.cos() * distance;
-        let new_y = self.y - self.direction.sin() * distance;
+    pub fn strafe_right(&mut self, distance: f64, map: &Map) -> bool {
+        let dx = -self.direction.sin() * distance;
+        let dy = self.direction.cos() * distance;
+        self.move_by(dx, dy, map)
+    }
+
+    // Mueve cada eje por separado para deslizarse a lo largo de las paredes. Devuelve si se
+    // ha llegado a mover, para que suenen los pasos.
+    fn move_by(&mut self, dx: f64, dy: f64, map: &Map) -> bool {
+        let new_x = self.x + dx;
+        let new_y = self.y + dy;
+        let mut moved = false;
 
-        if !map.is_wall(new_x, self.y) {
+        if !self.collides_at(new_x, self.y, map) {
             self.x = new_x;
-            self.play_walk_sound(walk_sink);
+            moved = true;
         }
 
-        if !map.is_wall(self.x, new_y) {
+        if !self.collides_at(self.x, new_y, map) {
             self.y = new_y;
-            self.play_walk_sound(walk_sink);
+            moved = true;
         }
+        moved
     }
 
-    fn play_walk_sound(&self, walk_sink: &Sink) {
-        // Si el Sink está vacío, entonces reproducimos el sonido
-        if walk_sink.empty() {
-            let walk_file = BufReader::new(File::open("src/pasos.wav").unwrap());
-            let walk_source = Decoder::new(walk_file).unwrap();
-    
-            walk_sink.append(walk_source);
-            walk_sink.play(); // Asegúrate de que se reproduzca
-        }
+    // Comprueba las cuatro esquinas del cuadrado de lado 2 * radius centrado en (x, y);
+    // así el jugador no se acerca a menos de `radius` de ninguna cara ni esquina de pared
+    pub fn collides_at(&self, x: f64, y: f64, map: &Map) -> bool {
+        let r = self.radius;
+        map.is_wall(x - r, y - r) || map.is_wall(x + r, y - r) || map.is_wall(x - r, y + r) || map.is_wall(x + r, y + r)
+    }
+
+    // Acciona la puerta que el jugador tiene delante, si la hay; true si ha empezado a abrirse
+    pub fn use_door(&self, map: &mut Map) -> bool {
+        let (target_x, target_y) = self.use_target();
+        map.use_door(target_x, target_y)
+    }
+
+    // Punto delante del jugador donde se buscan las puertas al usarlas
+    pub fn use_target(&self) -> (f64, f64) {
+        (self.x + self.direction.cos() * USE_DISTANCE, self.y + self.direction.sin() * USE_DISTANCE)
     }
-    
 
     pub fn turn_left(&mut self, angle: f64) {
         self.direction -= angle;
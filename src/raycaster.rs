@@ -1,73 +1,184 @@
 use crate::player::Player;
-use crate::map::Map;
-pub fn cast_ray(map: &Map, player: &Player, angle_offset: f64) -> (f64, bool) {
+use crate::map::{Map, DOOR};
+
+pub struct RayHit {
+    pub distance: f64,       // Distancia a la pared
+    pub is_horizontal: bool, // Si se golpeó un lado horizontal
+    pub wall: i32,           // Tipo de pared golpeada (0 si el rayo salió del mapa)
+    pub wall_x: f64,         // Posición del impacto a lo largo de la pared, entre 0 y 1
+}
+
+// Recorrido DDA de las celdas de la rejilla que atraviesa un rayo
+struct GridWalk {
+    map_x: isize,
+    map_y: isize,
+    step_x: isize,
+    step_y: isize,
+    side_dist_x: f64, // Distancia del origen a la siguiente línea vertical de la rejilla
+    side_dist_y: f64, // Distancia del origen a la siguiente línea horizontal de la rejilla
+    delta_dist_x: f64, // Distancia recorrida por el rayo desde una pared a la siguiente
+    delta_dist_y: f64,
+}
+
+impl GridWalk {
+    fn new(x: f64, y: f64, dir_x: f64, dir_y: f64) -> Self {
+        // Posiciones iniciales en la rejilla
+        let map_x = x.floor() as isize;
+        let map_y = y.floor() as isize;
+
+        let delta_dist_x = (1.0 / dir_x).abs();
+        let delta_dist_y = (1.0 / dir_y).abs();
+
+        // Calcular paso inicial y distancia inicial a la primera intersección
+        let (step_x, side_dist_x) = if dir_x < 0.0 {
+            (-1, (x - map_x as f64) * delta_dist_x)
+        } else {
+            (1, (map_x as f64 + 1.0 - x) * delta_dist_x)
+        };
+        let (step_y, side_dist_y) = if dir_y < 0.0 {
+            (-1, (y - map_y as f64) * delta_dist_y)
+        } else {
+            (1, (map_y as f64 + 1.0 - y) * delta_dist_y)
+        };
+
+        Self { map_x, map_y, step_x, step_y, side_dist_x, side_dist_y, delta_dist_x, delta_dist_y }
+    }
+
+    // Salta al siguiente cuadrado; devuelve 0 si cruzó una línea vertical y 1 si horizontal
+    fn step(&mut self) -> i32 {
+        if self.side_dist_x < self.side_dist_y {
+            self.side_dist_x += self.delta_dist_x;
+            self.map_x += self.step_x;
+            0
+        } else {
+            self.side_dist_y += self.delta_dist_y;
+            self.map_y += self.step_y;
+            1
+        }
+    }
+
+    fn is_outside(&self, map: &Map) -> bool {
+        self.map_x < 0 || self.map_y < 0 || self.map_x as usize >= map.width || self.map_y as usize >= map.height
+    }
+}
+
+pub fn cast_ray(map: &Map, player: &Player, angle_offset: f64) -> RayHit {
     let ray_angle = player.direction + angle_offset;
 
     // Dirección del rayo
     let ray_dir_x = ray_angle.cos();
     let ray_dir_y = ray_angle.sin();
 
-    // Posiciones iniciales en la rejilla
-    let mut map_x = player.x.floor() as isize;
-    let mut map_y = player.y.floor() as isize;
-
-    // Distancia recorrida por el rayo desde una pared a la siguiente
-    let delta_dist_x = (1.0 / ray_dir_x.abs()).abs();
-    let delta_dist_y = (1.0 / ray_dir_y.abs()).abs();
-
-    // Variables de paso
-    let step_x;
-    let step_y;
-    let mut side_dist_x;
-    let mut side_dist_y;
-
-    // Calcular paso inicial y distancia inicial a la primera intersección
-    if ray_dir_x < 0.0 {
-        step_x = -1;
-        side_dist_x = (player.x - map_x as f64) * delta_dist_x;
-    } else {
-        step_x = 1;
-        side_dist_x = (map_x as f64 + 1.0 - player.x) * delta_dist_x;
-    }
-
-    if ray_dir_y < 0.0 {
-        step_y = -1;
-        side_dist_y = (player.y - map_y as f64) * delta_dist_y;
-    } else {
-        step_y = 1;
-        side_dist_y = (map_y as f64 + 1.0 - player.y) * delta_dist_y;
-    }
+    let mut walk = GridWalk::new(player.x, player.y, ray_dir_x, ray_dir_y);
 
-    let mut hit = false; // Si el rayo ha golpeado una pared
+    let mut wall = 0; // Tipo de pared golpeada, 0 mientras no haya impacto
     let mut side = 0; // 0 para vertical, 1 para horizontal
+    let mut door_hit = None; // Distancia y coordenada de textura si se golpeó una puerta
 
-    // Bucle para recorrer el mapa
-    while !hit {
-        // Saltar al siguiente cuadrado
-        if side_dist_x < side_dist_y {
-            side_dist_x += delta_dist_x;
-            map_x += step_x;
-            side = 0;
+    // Coordenada a lo largo de la pared del punto del rayo a una distancia dada
+    let along_wall = |distance: f64, side: i32| {
+        let position = if side == 0 {
+            player.y + distance * ray_dir_y
         } else {
-            side_dist_y += delta_dist_y;
-            map_y += step_y;
-            side = 1;
+            player.x + distance * ray_dir_x
+        };
+        position - position.floor()
+    };
+
+    // Bucle para recorrer el mapa
+    while wall == 0 {
+        side = walk.step();
+
+        // Si el rayo sale del mapa no hay pared que golpear
+        if walk.is_outside(map) {
+            break;
         }
 
-        // Comprobar si el rayo ha golpeado una pared
-        if map.is_wall(map_x as f64, map_y as f64) {
-            hit = true;
+        let cell = map.wall_at(walk.map_x as f64, walk.map_y as f64);
+
+        // Las puertas están hundidas hasta la mitad de la celda: el rayo solo las golpea
+        // si alcanza ese plano antes de salir de la celda y por la parte que sigue cerrada
+        if cell == DOOR {
+            let (door_dist, exit_dist) = if side == 0 {
+                (walk.side_dist_x - walk.delta_dist_x / 2.0, walk.side_dist_y)
+            } else {
+                (walk.side_dist_y - walk.delta_dist_y / 2.0, walk.side_dist_x)
+            };
+
+            if door_dist < exit_dist {
+                let open_amount = map
+                    .door_at(walk.map_x as f64, walk.map_y as f64)
+                    .map_or(0.0, |door| door.open_amount);
+                let door_x = along_wall(door_dist, side);
+                if door_x >= open_amount {
+                    door_hit = Some((door_dist, door_x - open_amount));
+                    wall = DOOR;
+                }
+            }
+            continue;
         }
+
+        // Comprobar si el rayo ha golpeado una pared
+        wall = cell;
     }
 
-    // Calcular distancia perpendicular a la pared para evitar distorsión
-    let perp_wall_dist = if side == 0 {
-        (map_x as f64 - player.x + (1.0 - step_x as f64) / 2.0) / ray_dir_x
-    } else {
-        (map_y as f64 - player.y + (1.0 - step_y as f64) / 2.0) / ray_dir_y
+    let (perp_wall_dist, wall_x) = match door_hit {
+        Some(door_hit) => door_hit,
+        None => {
+            // Calcular distancia perpendicular a la pared para evitar distorsión
+            let perp_wall_dist = if side == 0 {
+                (walk.map_x as f64 - player.x + (1.0 - walk.step_x as f64) / 2.0) / ray_dir_x
+            } else {
+                (walk.map_y as f64 - player.y + (1.0 - walk.step_y as f64) / 2.0) / ray_dir_y
+            };
+            (perp_wall_dist, along_wall(perp_wall_dist, side))
+        }
     };
-    
-    
 
-    (perp_wall_dist, side == 1)
+    RayHit {
+        distance: perp_wall_dist,
+        is_horizontal: side == 1,
+        wall,
+        wall_x,
+    }
+}
+
+// Recorre con DDA las celdas que atraviesa el segmento entre dos puntos y comprueba
+// que ninguna sea una pared (las puertas solo tapan mientras no estén abiertas).
+// Las celdas de los extremos no cuentan, así una pared o puerta se ve a sí misma.
+pub fn has_line_of_sight(map: &Map, from: (f64, f64), to: (f64, f64)) -> bool {
+    walls_between(map, from, to) == 0
+}
+
+// Cuántas paredes cruza el segmento entre dos puntos, sin contar las celdas de los
+// extremos. Salir del mapa cuenta como una pared más y termina el recorrido.
+pub fn walls_between(map: &Map, from: (f64, f64), to: (f64, f64)) -> usize {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx.hypot(dy);
+    if length == 0.0 {
+        return 0;
+    }
+
+    let mut walk = GridWalk::new(from.0, from.1, dx / length, dy / length);
+    let target = (to.0.floor() as isize, to.1.floor() as isize);
+    let mut walls = 0;
+
+    while (walk.map_x, walk.map_y) != target {
+        // Si la siguiente frontera queda más allá del destino ya no hay más celdas que cruzar
+        if walk.side_dist_x.min(walk.side_dist_y) > length {
+            break;
+        }
+        walk.step();
+
+        if (walk.map_x, walk.map_y) == target {
+            break;
+        }
+        if walk.is_outside(map) {
+            return walls + 1;
+        }
+        if map.is_wall(walk.map_x as f64 + 0.5, walk.map_y as f64 + 0.5) {
+            walls += 1;
+        }
+    }
+    walls
 }
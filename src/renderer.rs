@@ -0,0 +1,332 @@
+use crate::framebuffer::Framebuffer;
+use crate::item::Item;
+use crate::lighting::modulate;
+use crate::map::Map;
+use crate::parallel::RenderPool;
+use crate::player::Player;
+use crate::raycaster::cast_ray;
+use crate::textures::{alpha, rgb, Texture, WallTextures};
+
+pub const COLOR_CIELO: u32 = 0x87CEEB; // Celeste
+pub const COLOR_SUELO: u32 = 0x8B4513; // Café
+
+// Imagen del mundo que se dibuja de frente a la cámara
+pub struct Sprite<'a> {
+    pub x: f64,
+    pub y: f64,
+    pub image: &'a Texture,
+}
+
+// Los píxeles con menos opacidad que esta no se dibujan
+const ALPHA_THRESHOLD: u8 = 128;
+
+pub fn render_scene(
+    map: &Map,
+    player: &Player,
+    framebuffer: &mut Framebuffer,
+    wall_textures: &WallTextures,
+    sprites: &[Sprite],
+    pool: &RenderPool,
+) {
+    let depth_buffer = render_walls(map, player, framebuffer, wall_textures, pool);
+    draw_sprites(framebuffer, &depth_buffer, sprites, player, map);
+}
+
+// Dibuja cielo, suelo y paredes sin ningún objeto encima.
+// Devuelve la distancia a la pared de cada columna para ocultar los sprites detrás.
+//
+// Las columnas se renderizan por franjas en un búfer por columnas, repartidas entre los
+// hilos de `pool`, y después se copian al framebuffer fila a fila. Cada columna solo
+// depende de su rayo, así que la imagen es idéntica con cualquier número de hilos.
+pub fn render_walls(
+    map: &Map,
+    player: &Player,
+    framebuffer: &mut Framebuffer,
+    wall_textures: &WallTextures,
+    pool: &RenderPool,
+) -> Vec<f64> {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    if width == 0 || height == 0 {
+        return vec![f64::INFINITY; width];
+    }
+
+    let mut columns = vec![0; width * height];
+    let stripes = pool.map_chunks(&mut columns, height * STRIPE_WIDTH, |stripe, pixels| {
+        pixels
+            .chunks_mut(height)
+            .enumerate()
+            .map(|(i, column)| render_column(map, player, wall_textures, stripe * STRIPE_WIDTH + i, width, column))
+            .collect::<Vec<f64>>()
+    });
+
+    // Pasar de columnas a filas, también repartido entre los hilos
+    pool.map_chunks(&mut framebuffer.buffer, width, |y, row| {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            *pixel = columns[x * height + y];
+        }
+    });
+
+    stripes.concat()
+}
+
+const STRIPE_WIDTH: usize = 16; // Columnas que renderiza cada tarea
+
+// Renderiza la columna `x` de una pantalla de ancho `width` en `column`, de arriba a abajo,
+// y devuelve la distancia a la pared (infinita si no hay)
+fn render_column(
+    map: &Map,
+    player: &Player,
+    wall_textures: &WallTextures,
+    x: usize,
+    width: usize,
+    column: &mut [u32],
+) -> f64 {
+    let height = column.len();
+    let shading = &map.shading;
+    let lightmap = &map.lightmap;
+
+    let camera_x = 2.0 * (x as f64) / (width as f64) - 1.0;
+    let ray_angle = player.direction + player.fov / 2.0 * camera_x;
+
+    let ray_dir_x = ray_angle.cos();
+    let ray_dir_y = ray_angle.sin();
+
+    let hit = cast_ray(map, player, player.fov / 2.0 * camera_x);
+    let perp_wall_dist = hit.distance;
+
+    if perp_wall_dist <= 0.0 {
+        column.fill(0);
+        return f64::INFINITY;
+    }
+
+    let wall_height = (height as f64 / (perp_wall_dist + 0.1)) as usize;
+    let wall_height = wall_height.min(height);
+
+    let start = (height / 2).saturating_sub(wall_height / 2);
+    let end = (height / 2).saturating_add(wall_height / 2);
+
+    // Renderizar cielo y suelo antes de la pared, proyectando cada fila sobre el mapa:
+    // una fila a `p` píxeles del horizonte ve el suelo a la distancia en la que
+    // una pared mediría `2p` píxeles de alto
+    let half_height = height as f64 / 2.0;
+    for (y, pixel) in column.iter_mut().enumerate().take(start) {
+        let row_dist = half_height / (half_height - y as f64 - 0.5) - 0.1;
+        let ceiling_x = player.x + row_dist * ray_dir_x;
+        let ceiling_y = player.y + row_dist * ray_dir_y;
+        // El cielo abierto está infinitamente lejos y no recibe niebla
+        *pixel = match map.ceiling_at(ceiling_x, ceiling_y) {
+            0 => COLOR_CIELO,
+            texture => {
+                let color = sample_surface(wall_textures.get(texture), ceiling_x, ceiling_y);
+                let color = lightmap.apply(map, color, ceiling_x, ceiling_y);
+                shading.apply_fog(color, row_dist)
+            }
+        };
+    }
+    for (y, pixel) in column.iter_mut().enumerate().skip(end) {
+        let row_dist = half_height / (y as f64 + 0.5 - half_height) - 0.1;
+        let floor_x = player.x + row_dist * ray_dir_x;
+        let floor_y = player.y + row_dist * ray_dir_y;
+        let color = match map.floor_at(floor_x, floor_y) {
+            0 => COLOR_SUELO,
+            texture => sample_surface(wall_textures.get(texture), floor_x, floor_y),
+        };
+        let color = lightmap.apply(map, color, floor_x, floor_y);
+        *pixel = shading.apply_fog(color, row_dist);
+    }
+
+    let wall_texture = wall_textures.get(hit.wall);
+    let texture_height = wall_texture.height() as usize;
+    let texels = wall_texture.column(wall_texture.texel_x(hit.wall_x));
+
+    // La pared se ilumina con la luz del lado desde el que se ve, justo antes del impacto
+    let light_dist = (perp_wall_dist - 0.01).max(0.0);
+    let wall_light = lightmap.sample(map, player.x + light_dist * ray_dir_x, player.y + light_dist * ray_dir_y);
+    for (y, pixel) in column.iter_mut().enumerate().take(end).skip(start) {
+        // `y - start` nunca llega a `wall_height`, así que la fila siempre cae dentro de la textura
+        let tex_y = ((y - start) * texture_height) / wall_height;
+        let color = modulate(rgb(texels[tex_y]), wall_light);
+        *pixel = shading.shade_wall(color, perp_wall_dist, hit.is_horizontal);
+    }
+
+    perp_wall_dist
+}
+
+// Color de la textura de suelo o techo en un punto del mapa; cada celda la repite entera
+fn sample_surface(texture: &Texture, x: f64, y: f64) -> u32 {
+    rgb(texture.get(texture.texel_x(x - x.floor()), texture.texel_y(y - y.floor())))
+}
+
+// Dibuja los sprites del más lejano al más cercano para que los cercanos queden encima
+pub fn draw_sprites(
+    framebuffer: &mut Framebuffer,
+    depth_buffer: &[f64],
+    sprites: &[Sprite],
+    player: &Player,
+    map: &Map,
+) {
+    let distance = |sprite: &Sprite| (sprite.x - player.x).hypot(sprite.y - player.y);
+
+    let mut order: Vec<&Sprite> = sprites.iter().collect();
+    order.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+
+    for sprite in order {
+        draw_sprite(framebuffer, depth_buffer, sprite, player, map);
+    }
+}
+
+// Dibuja la imagen de un sprite escalada por la distancia, columna por columna,
+// sin pintar las columnas en las que la pared está más cerca que el sprite
+pub fn draw_sprite(
+    framebuffer: &mut Framebuffer,
+    depth_buffer: &[f64],
+    sprite: &Sprite,
+    player: &Player,
+    map: &Map,
+) {
+    let sprite_dist_x = sprite.x - player.x;
+    let sprite_dist_y = sprite.y - player.y;
+
+    // Se proyecta igual que las paredes: la columna depende del ángulo respecto a la
+    // dirección del jugador y la altura de la distancia al jugador
+    let distance = sprite_dist_x.hypot(sprite_dist_y);
+    let mut angle = sprite_dist_y.atan2(sprite_dist_x) - player.direction;
+    angle = (angle + std::f64::consts::PI).rem_euclid(2.0 * std::f64::consts::PI) - std::f64::consts::PI;
+
+    // Evitar sprites detrás del jugador o pegados a la cámara
+    if angle.abs() >= std::f64::consts::FRAC_PI_2 || distance < f64::EPSILON {
+        return;
+    }
+
+    let sprite_screen_x = ((framebuffer.width as f64 / 2.0) * (1.0 + angle / (player.fov / 2.0))) as isize;
+
+    // Tamaño y posición del sprite, conservando la proporción de la imagen
+    let (image_width, image_height) = (sprite.image.width(), sprite.image.height());
+    let sprite_height = (framebuffer.height as f64 / (distance + 0.1)) as isize;
+    let sprite_width = sprite_height * image_width as isize / image_height as isize;
+    if sprite_width <= 0 || sprite_height <= 0 {
+        return;
+    }
+
+    // Esquina superior izquierda sin recortar, para calcular las coordenadas de la textura
+    let left = sprite_screen_x - sprite_width / 2;
+    let top = framebuffer.height as isize / 2 - sprite_height / 2;
+
+    // Calcular inicio y fin de dibujo, asegurando que no haya desbordamientos
+    let draw_start_y = top.max(0);
+    let draw_end_y = (top + sprite_height).min(framebuffer.height as isize);
+    let draw_start_x = left.max(0);
+    let draw_end_x = (left + sprite_width).min(framebuffer.width as isize);
+
+    // Todo el sprite recibe la luz de su posición en el mapa
+    let light = map.lightmap.sample(map, sprite.x, sprite.y);
+
+    // Dibujar la imagen del sprite, saltando las columnas tapadas por paredes
+    for x in draw_start_x..draw_end_x {
+        if distance >= depth_buffer[x as usize] {
+            continue;
+        }
+        let tex_x = ((x - left) * image_width as isize / sprite_width) as u32;
+        let texels = sprite.image.column(tex_x.min(image_width - 1));
+        for y in draw_start_y..draw_end_y {
+            let tex_y = ((y - top) * image_height as isize / sprite_height) as usize;
+            let texel = texels[tex_y.min(image_height as usize - 1)];
+
+            // Saltar los píxeles transparentes
+            if alpha(texel) < ALPHA_THRESHOLD {
+                continue;
+            }
+            framebuffer.point(x as usize, y as usize, map.shading.apply_fog(modulate(rgb(texel), light), distance));
+        }
+    }
+}
+
+pub fn draw_minimap(map: &Map, player: &Player, framebuffer: &mut Framebuffer, key: &Item, goal: &Item) {
+    let minimap_scale = 4;
+
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let color = if map.is_wall(x as f64, y as f64) {
+                0xFFFFFF
+            } else {
+                0x000000
+            };
+
+            for py in 0..minimap_scale {
+                for px in 0..minimap_scale {
+                    framebuffer.point(x * minimap_scale + px, y * minimap_scale + py, color);
+                }
+            }
+        }
+    }
+
+    let player_x = (player.x * minimap_scale as f64) as usize;
+    let player_y = (player.y * minimap_scale as f64) as usize;
+    for py in 0..minimap_scale {
+        for px in 0..minimap_scale {
+            framebuffer.point(player_x + px, player_y + py, 0xFF0000);
+        }
+    }
+
+    if !key.collected {
+        let key_x = (key.x * minimap_scale as f64) as usize;
+        let key_y = (key.y * minimap_scale as f64) as usize;
+        for py in 0..minimap_scale {
+            for px in 0..minimap_scale {
+                framebuffer.point(key_x + px, key_y + py, 0xFFFF00);
+            }
+        }
+    }
+
+    let goal_x = (goal.x * minimap_scale as f64) as usize;
+    let goal_y = (goal.y * minimap_scale as f64) as usize;
+    for py in 0..minimap_scale {
+        for px in 0..minimap_scale {
+            framebuffer.point(goal_x + px, goal_y + py, 0x00FF00);
+        }
+    }
+
+    for enemy in &map.enemies {
+        let enemy_x = (enemy.x * minimap_scale as f64) as usize;
+        let enemy_y = (enemy.y * minimap_scale as f64) as usize;
+        for py in 0..minimap_scale {
+            for px in 0..minimap_scale {
+                framebuffer.point(enemy_x + px, enemy_y + py, 0xFF00FF);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::MapSource;
+
+    // La imagen y las distancias no pueden depender de cuántos hilos renderizan
+    #[test]
+    fn render_walls_is_identical_with_any_number_of_threads() {
+        let mut map = MapSource::File("src/maps/level1.map".to_string()).load().unwrap();
+        let wall_textures = WallTextures::load(&map).unwrap();
+        let pools = [RenderPool::serial(), RenderPool::new(4).unwrap(), RenderPool::new(3).unwrap()];
+
+        // Un ancho que no es múltiplo de las franjas, para probar también la última
+        let (width, height) = (203, 117);
+        for direction in [0.0, 1.0, 2.5, 4.0] {
+            let player = Player::new(map.spawn_x, map.spawn_y, direction);
+            map.update_lights(&[]);
+
+            let renders: Vec<(Vec<u32>, Vec<f64>)> = pools
+                .iter()
+                .map(|pool| {
+                    let mut framebuffer = Framebuffer::new(width, height);
+                    let depth_buffer = render_walls(&map, &player, &mut framebuffer, &wall_textures, pool);
+                    (framebuffer.buffer, depth_buffer)
+                })
+                .collect();
+            for (buffer, depth_buffer) in &renders[1..] {
+                assert!(buffer == &renders[0].0, "framebuffer differs facing {}", direction);
+                assert_eq!(depth_buffer, &renders[0].1, "depth buffer differs facing {}", direction);
+            }
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::game::{Game, Outcome};
+use crate::input::Action;
+use crate::map::{Map, MapError};
+use crate::maze::{generate, MazeOptions};
+
+// Se sube cuando cambia el formato o lo que hace una misma grabación (como el laberinto que
+// genera una semilla), para no reproducir mal las grabaciones antiguas
+const REPLAY_VERSION: u32 = 4;
+
+// De dónde sale el mapa de una partida
+#[derive(Clone, Debug)]
+pub enum MapSource {
+    File(String),
+    Maze(MazeOptions),
+}
+
+impl MapSource {
+    pub fn load(&self) -> Result<Map, MapError> {
+        match self {
+            MapSource::File(path) => Map::load_from_path(path),
+            MapSource::Maze(options) => Ok(generate(options)),
+        }
+    }
+
+    // Línea con la que se guarda en los archivos de partidas:
+    // `map RUTA` o `maze ALGORITMO ANCHO ALTO SEMILLA BRAID SALAS`
+    pub fn to_line(&self) -> String {
+        match self {
+            MapSource::File(path) => format!("map {}", path),
+            MapSource::Maze(maze) => format!(
+                "maze {} {} {} {} {:?} {}",
+                maze.algorithm, maze.width, maze.height, maze.seed, maze.braid, maze.rooms
+            ),
+        }
+    }
+
+    // Lee una línea escrita con `to_line`, ya separada en clave y resto; None si la clave
+    // no es `map` ni `maze`
+    pub fn parse_line(key: &str, rest: &str) -> Option<Result<MapSource, String>> {
+        match key {
+            "map" => Some(Ok(MapSource::File(rest.trim().to_string()))),
+            "maze" => Some(parse_maze(&rest.split_whitespace().collect::<Vec<_>>())),
+            _ => None,
+        }
+    }
+}
+
+fn parse_maze(args: &[&str]) -> Result<MapSource, String> {
+    if args.len() != 6 {
+        return Err("`maze` expects an algorithm, a width, a height, a seed, a braid and a room count".to_string());
+    }
+    let mut maze = MazeOptions::new(parse_arg(args, 1)?, parse_arg(args, 2)?, parse_arg(args, 3)?);
+    maze.algorithm = parse_arg(args, 0)?;
+    maze.braid = parse_arg(args, 4)?;
+    maze.rooms = parse_arg(args, 5)?;
+    Ok(MapSource::Maze(maze))
+}
+
+impl fmt::Display for MapSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapSource::File(path) => write!(f, "{}", path),
+            MapSource::Maze(maze) => write!(f, "{} maze with seed {}", maze.algorithm, maze.seed),
+        }
+    }
+}
+
+// Entrada de un tick de la partida: tiempo transcurrido, acciones y giro con el ratón
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Tick {
+    pub dt: f64,
+    pub down: u32,    // Acciones pulsadas, un bit por acción
+    pub pressed: u32, // Acciones que se empezaron a pulsar en este tick
+    pub mouse_turn: f64, // Radianes, ya multiplicado por la sensibilidad del ratón
+}
+
+impl Tick {
+    pub fn is_down(&self, action: Action) -> bool {
+        self.down & action.mask() != 0
+    }
+
+    pub fn was_pressed(&self, action: Action) -> bool {
+        self.pressed & action.mask() != 0
+    }
+}
+
+// Estado final de la partida grabada, para comprobar que la reproducción coincide
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ReplayResult {
+    pub outcome: Outcome,
+    pub x: f64,
+    pub y: f64,
+    pub direction: f64,
+    pub key_collected: bool,
+}
+
+impl ReplayResult {
+    pub fn from_game(game: &Game) -> Self {
+        Self {
+            outcome: game.outcome,
+            x: game.player.x,
+            y: game.player.y,
+            direction: game.player.direction,
+            key_collected: game.key.collected,
+        }
+    }
+}
+
+impl fmt::Display for ReplayResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at ({:?}, {:?}) facing {:?}, key {}",
+            self.outcome,
+            self.x,
+            self.y,
+            self.direction,
+            if self.key_collected { "collected" } else { "not collected" }
+        )
+    }
+}
+
+// Grabación de una partida: con el mismo mapa, la misma semilla y los mismos ticks
+// `Game::step` vuelve a dar exactamente la misma partida
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub map: MapSource,
+    pub seed: u64,
+    pub ticks: Vec<Tick>,
+    pub result: Option<ReplayResult>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{}", err),
+            ReplayError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(err: std::io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl Replay {
+    pub fn new(map: MapSource, seed: u64) -> Self {
+        Self { map, seed, ticks: Vec::new(), result: None }
+    }
+
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        let contents = fs::read_to_string(path)?;
+        contents.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+// Formato de los archivos de repetición:
+//
+//   version 4
+//   map src/maps/level1.map          (o: maze ALGORITMO ANCHO ALTO SEMILLA BRAID SALAS)
+//   seed 1234
+//   tick DT PULSADAS NUEVAS GIRO_RATON
+//   ...
+//   result RESULTADO X Y DIRECCION LLAVE
+//
+// Los números con decimales se escriben con todos los dígitos necesarios para leerlos
+// de vuelta sin ninguna diferencia, y las acciones como máscaras de bits.
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "version {}", REPLAY_VERSION)?;
+        writeln!(f, "{}", self.map.to_line())?;
+        writeln!(f, "seed {}", self.seed)?;
+        for tick in &self.ticks {
+            writeln!(f, "tick {:?} {} {} {:?}", tick.dt, tick.down, tick.pressed, tick.mouse_turn)?;
+        }
+        if let Some(result) = &self.result {
+            writeln!(
+                f,
+                "result {} {:?} {:?} {:?} {}",
+                result.outcome, result.x, result.y, result.direction, result.key_collected
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Replay {
+    type Err = ReplayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = None;
+        let mut seed = None;
+        let mut ticks = Vec::new();
+        let mut result = None;
+        let mut version = None;
+
+        for (i, line) in s.lines().enumerate() {
+            let line_no = i + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| ReplayError::Parse { line: line_no, message };
+            let (key, rest) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+            let args: Vec<&str> = rest.split_whitespace().collect();
+
+            if let Some(source) = MapSource::parse_line(key, rest) {
+                map = Some(source.map_err(error)?);
+                continue;
+            }
+            match key {
+                "version" => {
+                    let value: u32 = parse_arg(&args, 0).map_err(error)?;
+                    if value != REPLAY_VERSION {
+                        return Err(error(format!("unsupported replay version {}", value)));
+                    }
+                    version = Some(value);
+                }
+                "seed" => seed = Some(parse_arg(&args, 0).map_err(error)?),
+                "tick" => {
+                    if args.len() != 4 {
+                        return Err(error("`tick` expects a time step, two action masks and a mouse turn".to_string()));
+                    }
+                    ticks.push(Tick {
+                        dt: parse_arg(&args, 0).map_err(error)?,
+                        down: parse_arg(&args, 1).map_err(error)?,
+                        pressed: parse_arg(&args, 2).map_err(error)?,
+                        mouse_turn: parse_arg(&args, 3).map_err(error)?,
+                    });
+                }
+                "result" => {
+                    if args.len() != 5 {
+                        return Err(error(
+                            "`result` expects an outcome, a position, a direction and whether the key was collected"
+                                .to_string(),
+                        ));
+                    }
+                    result = Some(ReplayResult {
+                        outcome: parse_arg(&args, 0).map_err(error)?,
+                        x: parse_arg(&args, 1).map_err(error)?,
+                        y: parse_arg(&args, 2).map_err(error)?,
+                        direction: parse_arg(&args, 3).map_err(error)?,
+                        key_collected: parse_arg(&args, 4).map_err(error)?,
+                    });
+                }
+                _ => return Err(error(format!("unknown key `{}`", key))),
+            }
+        }
+
+        let last_line = s.lines().count().max(1);
+        let missing = |what: &str| ReplayError::Parse { line: last_line, message: format!("missing `{}`", what) };
+        version.ok_or_else(|| missing("version"))?;
+        let map = map.ok_or_else(|| missing("map"))?;
+        let seed = seed.ok_or_else(|| missing("seed"))?;
+
+        Ok(Replay { map, seed, ticks, result })
+    }
+}
+
+// Lee el argumento `index` de una línea, con un mensaje de error legible si falta o no vale
+pub fn parse_arg<T: FromStr>(args: &[&str], index: usize) -> Result<T, String> {
+    let Some(value) = args.get(index) else {
+        return Err(format!("missing value {}", index + 1));
+    };
+    value.parse().map_err(|_| format!("invalid value `{}`", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::maze::MazeOptions;
+
+    const MOVES: [Action; 6] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::TurnLeft,
+        Action::TurnRight,
+    ];
+
+    // Graba una partida con acciones y tiempos al azar, la pasa a texto y la vuelve a leer
+    fn record(source: MapSource, seed: u64, ticks: usize) -> Replay {
+        let mut game = Game::new(source.load().unwrap(), seed).unwrap();
+        let mut replay = Replay::new(source, seed);
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..ticks {
+            let down = MOVES.iter().filter(|_| rng.gen_bool(0.4)).fold(0, |mask, action| mask | action.mask());
+            let pressed = if rng.gen_bool(0.05) { Action::Use.mask() } else { 0 };
+            let tick = Tick { dt: rng.gen_range(0.005..0.05), down, pressed, mouse_turn: rng.gen_range(-0.05..0.05) };
+            game.step(&tick);
+            replay.ticks.push(tick);
+        }
+        replay.result = Some(ReplayResult::from_game(&game));
+        replay
+    }
+
+    fn play(replay: &Replay) -> ReplayResult {
+        let mut game = Game::new(replay.map.load().unwrap(), replay.seed).unwrap();
+        for tick in &replay.ticks {
+            game.step(tick);
+        }
+        ReplayResult::from_game(&game)
+    }
+
+    fn assert_round_trip(replay: Replay) {
+        let parsed: Replay = replay.to_string().parse().unwrap();
+        assert_eq!(parsed.seed, replay.seed);
+        assert_eq!(parsed.ticks, replay.ticks);
+        assert_eq!(parsed.result, replay.result);
+        assert_eq!(Some(play(&parsed)), replay.result);
+    }
+
+    #[test]
+    fn replay_of_a_map_file_plays_back_identically() {
+        for seed in [1, 7, 42] {
+            assert_round_trip(record(MapSource::File("src/maps/level1.map".to_string()), seed, 3000));
+        }
+    }
+
+    #[test]
+    fn replay_of_a_generated_maze_plays_back_identically() {
+        let mut maze = MazeOptions::new(15, 15, 9);
+        maze.braid = 0.3;
+        assert_round_trip(record(MapSource::Maze(maze), 9, 3000));
+    }
+}
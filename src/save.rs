@@ -0,0 +1,442 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::door::{Door, DoorState};
+use crate::enemy::EnemyState;
+use crate::game::Game;
+use crate::item::Item;
+use crate::map::{Map, MapError, DOOR};
+use crate::player::Player;
+use crate::replay::{parse_arg, MapSource};
+
+// Se sube cada vez que cambia el formato o lo que genera una semilla; las partidas de otras
+// versiones no se cargan
+pub const SAVE_VERSION: u32 = 4;
+const SAVE_DIR: &str = "raycasting";
+const SAVE_FILE: &str = "save.txt";
+
+// Dónde estaba un enemigo y qué hacía; la ruta de patrulla sale del mapa
+#[derive(Clone, Copy, Debug)]
+pub struct SavedEnemy {
+    pub x: f64,
+    pub y: f64,
+    pub state: EnemyState,
+    pub next_waypoint: usize,
+}
+
+// Partida guardada: qué mapa se jugaba y cómo estaban el jugador, la llave, la meta, los
+// enemigos y las puertas. Al cargarla no se vuelven a colocar la llave ni la meta.
+#[derive(Clone, Debug)]
+pub struct SaveGame {
+    pub map: MapSource,
+    pub seed: u64,
+    pub elapsed: f64,
+    pub x: f64,
+    pub y: f64,
+    pub direction: f64,
+    pub fov: f64,
+    pub plane_x: f64,
+    pub plane_y: f64,
+    pub key: Item,
+    pub goal: Item,
+    pub enemies: Vec<SavedEnemy>, // En el orden del mapa
+    pub doors: Vec<((usize, usize), Door)>, // Solo las que no están cerradas
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+    Version(u32),      // Guardada con otra versión del formato
+    Map(MapError),     // El mapa de la partida ya no se puede cargar
+    Invalid(String),   // Se lee bien pero no encaja con el mapa
+    NoDataDir,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "{}", err),
+            SaveError::Parse { line, message } => write!(f, "save file is corrupted (line {}: {})", line, message),
+            SaveError::Version(version) => write!(
+                f,
+                "save file uses format version {} and this version of the game reads version {}",
+                version, SAVE_VERSION
+            ),
+            SaveError::Map(err) => write!(f, "the saved map can no longer be loaded: {}", err),
+            SaveError::Invalid(message) => write!(f, "save file does not match its map: {}", message),
+            SaveError::NoDataDir => write!(f, "no user data directory (set XDG_DATA_HOME, HOME or APPDATA)"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+// Carpeta de datos del usuario: $XDG_DATA_HOME, ~/.local/share o %APPDATA%
+pub fn data_dir() -> Option<PathBuf> {
+    let non_empty = |name: &str| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    non_empty("XDG_DATA_HOME")
+        .or_else(|| non_empty("HOME").map(|home| home.join(".local").join("share")))
+        .or_else(|| non_empty("APPDATA"))
+        .map(|dir| dir.join(SAVE_DIR))
+}
+
+// Archivo donde se guarda la partida
+pub fn save_path() -> Result<PathBuf, SaveError> {
+    data_dir().map(|dir| dir.join(SAVE_FILE)).ok_or(SaveError::NoDataDir)
+}
+
+// Puertas que no están cerradas, ordenadas para que el archivo salga siempre igual
+fn doors(map: &Map) -> Vec<((usize, usize), Door)> {
+    let mut doors: Vec<_> = map
+        .doors
+        .iter()
+        .filter(|(_, door)| door.state != DoorState::Closed)
+        .map(|(&cell, &door)| (cell, door))
+        .collect();
+    doors.sort_by_key(|&((x, y), _)| (y, x));
+    doors
+}
+
+// Las posiciones guardadas tienen que caer dentro del mapa y fuera de las paredes
+fn check_position(map: &Map, name: &str, x: f64, y: f64) -> Result<(), SaveError> {
+    let inside = x.is_finite() && y.is_finite() && x >= 0.0 && y >= 0.0;
+    if !inside || x as usize >= map.width || y as usize >= map.height {
+        return Err(SaveError::Invalid(format!("the {} is outside the map", name)));
+    }
+    let cell = map.wall_at(x, y);
+    if cell > 0 && cell != DOOR {
+        return Err(SaveError::Invalid(format!("the {} is inside a wall", name)));
+    }
+    Ok(())
+}
+
+impl SaveGame {
+    pub fn from_game(game: &Game, map: &MapSource) -> Self {
+        // La ruta del mapa se guarda absoluta para poder cargarla desde cualquier carpeta
+        let map = match map {
+            MapSource::File(path) => match fs::canonicalize(path) {
+                Ok(path) => MapSource::File(path.to_string_lossy().into_owned()),
+                Err(_) => MapSource::File(path.clone()),
+            },
+            source => source.clone(),
+        };
+        let player = &game.player;
+        Self {
+            map,
+            seed: game.seed,
+            elapsed: game.elapsed,
+            x: player.x,
+            y: player.y,
+            direction: player.direction,
+            fov: player.fov,
+            plane_x: player.plane_x,
+            plane_y: player.plane_y,
+            key: game.key,
+            goal: game.goal,
+            enemies: game
+                .map
+                .enemies
+                .iter()
+                .map(|enemy| SavedEnemy {
+                    x: enemy.x,
+                    y: enemy.y,
+                    state: enemy.state,
+                    next_waypoint: enemy.next_waypoint(),
+                })
+                .collect(),
+            doors: doors(&game.map),
+        }
+    }
+
+    // Vuelve a montar la partida: carga el mapa y coloca todo como estaba al guardar
+    pub fn restore(&self) -> Result<Game, SaveError> {
+        let mut map = self.map.load().map_err(SaveError::Map)?;
+
+        if self.enemies.len() != map.enemies.len() {
+            return Err(SaveError::Invalid(format!(
+                "the map has {} enemies and the save has {}",
+                map.enemies.len(),
+                self.enemies.len()
+            )));
+        }
+        let mut positions = vec![
+            ("player".to_string(), self.x, self.y),
+            ("key".to_string(), self.key.x, self.key.y),
+            ("goal".to_string(), self.goal.x, self.goal.y),
+        ];
+        for (i, enemy) in self.enemies.iter().enumerate() {
+            positions.push((format!("enemy {}", i + 1), enemy.x, enemy.y));
+        }
+        for (name, x, y) in positions {
+            check_position(&map, &name, x, y)?;
+        }
+        for &((x, y), door) in &self.doors {
+            if !map.doors.contains_key(&(x, y)) {
+                return Err(SaveError::Invalid(format!("there is no door at {} {}", x, y)));
+            }
+            if !(0.0..=1.0).contains(&door.open_amount) || !door.timer.is_finite() {
+                return Err(SaveError::Invalid(format!("the door at {} {} has an invalid state", x, y)));
+            }
+        }
+
+        for (enemy, saved) in map.enemies.iter_mut().zip(&self.enemies) {
+            enemy.restore(saved.x, saved.y, saved.state, saved.next_waypoint);
+        }
+        for &(cell, door) in &self.doors {
+            map.doors.insert(cell, door);
+        }
+
+        let mut player = Player::new(self.x, self.y, self.direction);
+        player.fov = self.fov;
+        player.plane_x = self.plane_x;
+        player.plane_y = self.plane_y;
+        let mut game = Game::with_items(map, player, self.key, self.goal, self.seed);
+        game.elapsed = self.elapsed;
+        Ok(game)
+    }
+
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<SaveGame, SaveError> {
+        let contents = fs::read_to_string(path)?;
+        contents.parse()
+    }
+
+    // Escribe primero a un archivo temporal para no dejar una partida a medias si algo falla
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, self.to_string())?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
+
+// Formato de las partidas guardadas:
+//
+//   version 4
+//   map /ruta/al/mapa.map            (o: maze ALGORITMO ANCHO ALTO SEMILLA BRAID SALAS)
+//   seed 1234
+//   elapsed SEGUNDOS
+//   player X Y DIRECCION FOV PLANO_X PLANO_Y
+//   key X Y RECOGIDA
+//   goal X Y RECOGIDA
+//   enemy X Y ESTADO PUNTO_DE_PATRULLA        (uno por enemigo, en el orden del mapa)
+//   door X Y APERTURA ESTADO TIEMPO           (una por puerta que no esté cerrada)
+impl fmt::Display for SaveGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "version {}", SAVE_VERSION)?;
+        writeln!(f, "{}", self.map.to_line())?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "elapsed {:?}", self.elapsed)?;
+        writeln!(
+            f,
+            "player {:?} {:?} {:?} {:?} {:?} {:?}",
+            self.x, self.y, self.direction, self.fov, self.plane_x, self.plane_y
+        )?;
+        writeln!(f, "key {:?} {:?} {}", self.key.x, self.key.y, self.key.collected)?;
+        writeln!(f, "goal {:?} {:?} {}", self.goal.x, self.goal.y, self.goal.collected)?;
+        for enemy in &self.enemies {
+            writeln!(f, "enemy {:?} {:?} {} {}", enemy.x, enemy.y, enemy.state, enemy.next_waypoint)?;
+        }
+        for ((x, y), door) in &self.doors {
+            writeln!(f, "door {} {} {:?} {} {:?}", x, y, door.open_amount, door.state, door.timer)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SaveGame {
+    type Err = SaveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut version = None;
+        let mut map = None;
+        let mut seed = None;
+        let mut elapsed = None;
+        let mut player = None;
+        let mut key = None;
+        let mut goal = None;
+        let mut enemies = Vec::new();
+        let mut doors = Vec::new();
+
+        for (i, line) in s.lines().enumerate() {
+            let line_no = i + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| SaveError::Parse { line: line_no, message };
+            let (name, rest) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+            let args: Vec<&str> = rest.split_whitespace().collect();
+
+            // La versión va primero: si no coincide, el resto del archivo puede no tener sentido
+            if version.is_none() && name != "version" {
+                return Err(error("missing `version` at the start of the file".to_string()));
+            }
+            if let Some(source) = MapSource::parse_line(name, rest) {
+                map = Some(source.map_err(error)?);
+                continue;
+            }
+            let expect = |count: usize| {
+                if args.len() == count {
+                    Ok(())
+                } else {
+                    Err(error(format!("`{}` expects {} values, found {}", name, count, args.len())))
+                }
+            };
+            match name {
+                "version" => {
+                    let value: u32 = parse_arg(&args, 0).map_err(error)?;
+                    if value != SAVE_VERSION {
+                        return Err(SaveError::Version(value));
+                    }
+                    version = Some(value);
+                }
+                "seed" => seed = Some(parse_arg(&args, 0).map_err(error)?),
+                "elapsed" => elapsed = Some(parse_arg(&args, 0).map_err(error)?),
+                "player" => {
+                    expect(6)?;
+                    let values = (0..6).map(|i| parse_arg(&args, i)).collect::<Result<Vec<f64>, _>>().map_err(error)?;
+                    player = Some(values);
+                }
+                "key" | "goal" => {
+                    expect(3)?;
+                    let item = Item {
+                        x: parse_arg(&args, 0).map_err(error)?,
+                        y: parse_arg(&args, 1).map_err(error)?,
+                        collected: parse_arg(&args, 2).map_err(error)?,
+                    };
+                    if name == "key" {
+                        key = Some(item);
+                    } else {
+                        goal = Some(item);
+                    }
+                }
+                "enemy" => {
+                    expect(4)?;
+                    enemies.push(SavedEnemy {
+                        x: parse_arg(&args, 0).map_err(error)?,
+                        y: parse_arg(&args, 1).map_err(error)?,
+                        state: parse_arg(&args, 2).map_err(error)?,
+                        next_waypoint: parse_arg(&args, 3).map_err(error)?,
+                    });
+                }
+                "door" => {
+                    expect(5)?;
+                    let cell = (parse_arg(&args, 0).map_err(error)?, parse_arg(&args, 1).map_err(error)?);
+                    let door = Door {
+                        open_amount: parse_arg(&args, 2).map_err(error)?,
+                        state: parse_arg(&args, 3).map_err(error)?,
+                        timer: parse_arg(&args, 4).map_err(error)?,
+                    };
+                    doors.push((cell, door));
+                }
+                _ => return Err(error(format!("unknown key `{}`", name))),
+            }
+        }
+
+        let last_line = s.lines().count().max(1);
+        let missing = |what: &str| SaveError::Parse { line: last_line, message: format!("missing `{}`", what) };
+        version.ok_or_else(|| missing("version"))?;
+        let player = player.ok_or_else(|| missing("player"))?;
+
+        Ok(SaveGame {
+            map: map.ok_or_else(|| missing("map"))?,
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            elapsed: elapsed.ok_or_else(|| missing("elapsed"))?,
+            x: player[0],
+            y: player[1],
+            direction: player[2],
+            fov: player[3],
+            plane_x: player[4],
+            plane_y: player[5],
+            key: key.ok_or_else(|| missing("key"))?,
+            goal: goal.ok_or_else(|| missing("goal"))?,
+            enemies,
+            doors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::MazeOptions;
+    use crate::replay::{ReplayResult, Tick};
+
+    fn round_trip(save: &SaveGame) -> Game {
+        let parsed: SaveGame = save.to_string().parse().unwrap();
+        parsed.restore().unwrap()
+    }
+
+    #[test]
+    fn restore_keeps_enemies_and_doors() {
+        let source = MapSource::File("src/maps/level1.map".to_string());
+        let mut game = Game::new(source.load().unwrap(), 5).unwrap();
+        let idle = Tick { dt: 0.1, down: 0, pressed: 0, mouse_turn: 0.0 };
+        for _ in 0..20 {
+            game.step(&idle);
+        }
+        let (&cell, _) = game.map.doors.iter().min_by_key(|(&(x, y), _)| (y, x)).unwrap();
+        game.map.use_door(cell.0 as f64, cell.1 as f64);
+        game.map.update_doors(0.3, game.player.x, game.player.y, game.player.radius);
+
+        let restored = round_trip(&SaveGame::from_game(&game, &source));
+        assert_eq!(ReplayResult::from_game(&restored), ReplayResult::from_game(&game));
+        assert_eq!(restored.elapsed, game.elapsed);
+        for (a, b) in restored.map.enemies.iter().zip(&game.map.enemies) {
+            assert_eq!((a.x, a.y, a.state, a.next_waypoint()), (b.x, b.y, b.state, b.next_waypoint()));
+        }
+        let door = restored.map.doors[&cell];
+        assert_eq!((door.open_amount, door.state), (game.map.doors[&cell].open_amount, DoorState::Opening));
+    }
+
+    #[test]
+    fn restore_keeps_the_collected_key_and_moving_doors() {
+        let source = MapSource::File("src/maps/level1.map".to_string());
+        let mut game = Game::new(source.load().unwrap(), 3).unwrap();
+        game.key.collected = true;
+        let mut cells: Vec<_> = game.map.doors.keys().copied().collect();
+        cells.sort_by_key(|&(x, y)| (y, x));
+        let (opening, closing) = (cells[0], cells[1]);
+        game.map.doors.insert(opening, Door { open_amount: 0.4, state: DoorState::Opening, timer: 0.0 });
+        game.map.doors.insert(closing, Door { open_amount: 0.7, state: DoorState::Closing, timer: 3.2 });
+
+        let restored = round_trip(&SaveGame::from_game(&game, &source));
+        assert!(restored.key.collected);
+        assert_eq!(restored.seed, game.seed);
+        for cell in [opening, closing] {
+            let (a, b) = (restored.map.doors[&cell], game.map.doors[&cell]);
+            assert_eq!((a.open_amount, a.state, a.timer), (b.open_amount, b.state, b.timer));
+        }
+    }
+
+    // En un laberinto tan pequeño no caben la llave y la meta a la distancia mínima, pero una
+    // partida guardada ya las trae colocadas y tiene que poder cargarse
+    #[test]
+    fn restore_does_not_place_the_items_again() {
+        let source = MapSource::Maze(MazeOptions::new(5, 5, 1));
+        let map = source.load().unwrap();
+        let player = Player::new(map.spawn_x, map.spawn_y, map.spawn_direction);
+        let key = Item { x: 3.5, y: 1.5, collected: false };
+        let goal = Item { x: 1.5, y: 3.5, collected: false };
+        assert!(Game::new(source.load().unwrap(), 1).is_err());
+
+        let game = Game::with_items(map, player, key, goal, 1);
+        let restored = round_trip(&SaveGame::from_game(&game, &source));
+        assert_eq!((restored.key.x, restored.key.y), (key.x, key.y));
+        assert_eq!((restored.goal.x, restored.goal.y), (goal.x, goal.y));
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::framebuffer::Framebuffer;
+
+const BORDER_COLOR: u32 = 0x000000; // Color de las bandas alrededor de la imagen
+
+// Cómo se reparte la imagen renderizada en la ventana. En los dos casos se conserva la
+// proporción y lo que sobra se rellena con bandas negras.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScaleMode {
+    Integer, // Solo múltiplos enteros, para que todos los píxeles midan lo mismo
+    Fit,     // Ocupa todo lo que se pueda aunque algunos píxeles salgan más anchos que otros
+}
+
+impl ScaleMode {
+    pub const ALL: [ScaleMode; 2] = [ScaleMode::Integer, ScaleMode::Fit];
+}
+
+impl FromStr for ScaleMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "integer" => Ok(ScaleMode::Integer),
+            "fit" => Ok(ScaleMode::Fit),
+            _ => Err(format!("unknown scale mode `{}` (expected integer or fit)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ScaleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ScaleMode::Integer => "integer",
+            ScaleMode::Fit => "fit",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Rectángulo de la ventana donde se dibuja la imagen
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+// Dónde cae una imagen de `source` píxeles en una ventana de `target`, centrada. Si la
+// ventana es más pequeña que la imagen no cabe ningún múltiplo entero y se reduce como en `Fit`.
+pub fn viewport(source: (usize, usize), target: (usize, usize), mode: ScaleMode) -> Viewport {
+    let (source_width, source_height) = (source.0.max(1), source.1.max(1));
+    let (target_width, target_height) = target;
+
+    let scale = (target_width / source_width).min(target_height / source_height);
+    let (width, height) = if mode == ScaleMode::Integer && scale >= 1 {
+        (source_width * scale, source_height * scale)
+    } else if target_width * source_height <= target_height * source_width {
+        // La ventana es más estrecha que la imagen: manda el ancho
+        (target_width, source_height * target_width / source_width)
+    } else {
+        (source_width * target_height / source_height, target_height)
+    };
+
+    Viewport {
+        x: (target_width - width) / 2,
+        y: (target_height - height) / 2,
+        width,
+        height,
+    }
+}
+
+// Escala el framebuffer al tamaño de la ventana con el vecino más cercano. Guarda el buffer
+// de salida entre fotogramas y solo lo vuelve a preparar cuando cambia el tamaño.
+pub struct Scaler {
+    buffer: Vec<u32>,
+    width: usize,
+    height: usize,
+    source: (usize, usize),
+    viewport: Option<Viewport>,
+    columns: Vec<usize>, // Columna del framebuffer que va en cada columna del viewport
+}
+
+impl Scaler {
+    pub fn new() -> Self {
+        Self { buffer: Vec::new(), width: 0, height: 0, source: (0, 0), viewport: None, columns: Vec::new() }
+    }
+
+    // Devuelve la imagen lista para una ventana de `width` x `height`
+    pub fn present(&mut self, framebuffer: &Framebuffer, width: usize, height: usize, mode: ScaleMode) -> &[u32] {
+        let source = (framebuffer.width, framebuffer.height);
+        let viewport = viewport(source, (width, height), mode);
+
+        if (width, height) != (self.width, self.height) || source != self.source || self.viewport != Some(viewport) {
+            self.width = width;
+            self.height = height;
+            self.source = source;
+            self.buffer.clear();
+            self.buffer.resize(width * height, BORDER_COLOR);
+            self.columns = (0..viewport.width).map(|x| x * framebuffer.width / viewport.width).collect();
+            self.viewport = Some(viewport);
+        }
+
+        let mut previous_row = None;
+        for y in 0..viewport.height {
+            let source_y = y * framebuffer.height / viewport.height;
+            let start = (viewport.y + y) * width + viewport.x;
+
+            // Las filas que salen de la misma fila del framebuffer se copian de la anterior
+            if previous_row == Some(source_y) {
+                let previous = start - width;
+                self.buffer.copy_within(previous..previous + viewport.width, start);
+                continue;
+            }
+            let row = &framebuffer.buffer[source_y * framebuffer.width..(source_y + 1) * framebuffer.width];
+            for (pixel, &column) in self.buffer[start..start + viewport.width].iter_mut().zip(&self.columns) {
+                *pixel = row[column];
+            }
+            previous_row = Some(source_y);
+        }
+
+        &self.buffer
+    }
+}
+
+impl Default for Scaler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: usize, y: usize, width: usize, height: usize) -> Viewport {
+        Viewport { x, y, width, height }
+    }
+
+    #[test]
+    fn integer_viewports() {
+        let cases = [
+            ((320, 200), (640, 400), at(0, 0, 640, 400)),
+            ((320, 200), (800, 600), at(80, 100, 640, 400)),
+            ((320, 200), (1000, 500), at(180, 50, 640, 400)),
+            ((320, 200), (639, 399), at(159, 99, 320, 200)),
+            // Más pequeña que la imagen: se reduce como en `Fit`
+            ((320, 200), (160, 100), at(0, 0, 160, 100)),
+            ((320, 200), (100, 100), at(0, 19, 100, 62)),
+            ((0, 0), (10, 6), at(2, 0, 6, 6)),
+        ];
+        for (source, target, expected) in cases {
+            assert_eq!(viewport(source, target, ScaleMode::Integer), expected, "{:?} in {:?}", source, target);
+        }
+    }
+
+    #[test]
+    fn fit_viewports() {
+        let cases = [
+            ((320, 200), (640, 400), at(0, 0, 640, 400)),
+            ((320, 200), (800, 600), at(0, 50, 800, 500)),
+            ((320, 200), (1000, 500), at(100, 0, 800, 500)),
+            ((320, 200), (641, 401), at(0, 0, 641, 400)),
+            ((320, 200), (803, 600), at(0, 49, 803, 501)),
+            ((320, 200), (100, 100), at(0, 19, 100, 62)),
+        ];
+        for (source, target, expected) in cases {
+            assert_eq!(viewport(source, target, ScaleMode::Fit), expected, "{:?} in {:?}", source, target);
+        }
+    }
+
+    #[test]
+    fn present_fills_the_borders() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.buffer = vec![0x111111, 0x222222];
+        let mut scaler = Scaler::new();
+        let (a, b, border) = (0x111111, 0x222222, BORDER_COLOR);
+        let expected = [
+            border, border, border, border,
+            a, a, b, b,
+            a, a, b, b,
+            border, border, border, border,
+        ];
+        assert_eq!(scaler.present(&framebuffer, 4, 4, ScaleMode::Integer), expected);
+    }
+}
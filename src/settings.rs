@@ -0,0 +1,49 @@
+// Opciones que el jugador puede cambiar desde el menú de ajustes
+
+use crate::scaling::ScaleMode;
+
+pub const MAX_VOLUME: f32 = 4.0;
+const VOLUME_STEP: f32 = 0.1;
+const MIN_SENSITIVITY: f64 = 0.0005; // Radianes por píxel de ratón
+const MAX_SENSITIVITY: f64 = 0.01;
+const SENSITIVITY_STEP: f64 = 0.0005;
+const MIN_FOV: f64 = 40.0; // Grados
+const MAX_FOV: f64 = 110.0;
+const FOV_STEP: f64 = 5.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Settings {
+    pub volume: f32,
+    pub mouse_sensitivity: f64,
+    pub fov: f64, // En grados
+    pub scale_mode: ScaleMode,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self { volume: 0.5, mouse_sensitivity: 0.002, fov: 66.0, scale_mode: ScaleMode::Integer }
+    }
+}
+
+impl Settings {
+    // `steps` positivo sube el valor y negativo lo baja, siempre dentro de sus límites
+    pub fn change_volume(&mut self, steps: i32) {
+        self.volume = (self.volume + steps as f32 * VOLUME_STEP).clamp(0.0, MAX_VOLUME);
+    }
+
+    pub fn change_mouse_sensitivity(&mut self, steps: i32) {
+        self.mouse_sensitivity =
+            (self.mouse_sensitivity + steps as f64 * SENSITIVITY_STEP).clamp(MIN_SENSITIVITY, MAX_SENSITIVITY);
+    }
+
+    pub fn change_fov(&mut self, steps: i32) {
+        self.fov = (self.fov + steps as f64 * FOV_STEP).clamp(MIN_FOV, MAX_FOV);
+    }
+
+    // Pasa al siguiente modo de escalado, o al anterior, dando la vuelta
+    pub fn change_scale_mode(&mut self, steps: i32) {
+        let modes = ScaleMode::ALL;
+        let current = modes.iter().position(|&mode| mode == self.scale_mode).unwrap_or(0);
+        self.scale_mode = modes[(current as i32 + steps).rem_euclid(modes.len() as i32) as usize];
+    }
+}
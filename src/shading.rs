@@ -0,0 +1,106 @@
+// Niebla por distancia: entre `start` y `end` el color se mezcla con el de la niebla,
+// siguiendo una curva `falloff` (1.0 es lineal, mayor que 1 la concentra al fondo)
+#[derive(Clone, Copy, Debug)]
+pub struct Fog {
+    pub color: u32,
+    pub start: f64,
+    pub end: f64,
+    pub falloff: f64,
+}
+
+// Iluminación de un nivel
+#[derive(Clone, Copy, Debug)]
+pub struct Shading {
+    pub fog: Option<Fog>,
+    pub side_shade: f64, // Brillo de las caras norte/sur de las paredes respecto a las este/oeste (1.0 sin cambio)
+}
+
+impl Default for Shading {
+    fn default() -> Self {
+        Self { fog: None, side_shade: 1.0 }
+    }
+}
+
+impl Shading {
+    // Fracción de niebla a una distancia, entre 0 y 1
+    pub fn fog_amount(&self, distance: f64) -> f64 {
+        match self.fog {
+            Some(fog) if distance > fog.start => {
+                let t = ((distance - fog.start) / (fog.end - fog.start)).min(1.0);
+                t.powf(fog.falloff)
+            }
+            _ => 0.0,
+        }
+    }
+
+    // Mezcla el color con el de la niebla según la distancia
+    pub fn apply_fog(&self, color: u32, distance: f64) -> u32 {
+        match self.fog {
+            Some(fog) => mix(color, fog.color, self.fog_amount(distance)),
+            None => color,
+        }
+    }
+
+    // Las paredes se oscurecen según la cara golpeada antes de aplicar la niebla
+    pub fn shade_wall(&self, color: u32, distance: f64, is_horizontal: bool) -> u32 {
+        let color = if is_horizontal { scale(color, self.side_shade) } else { color };
+        self.apply_fog(color, distance)
+    }
+}
+
+fn scale(color: u32, factor: f64) -> u32 {
+    let channel = |shift: u32| ((((color >> shift) & 0xFF) as f64 * factor) as u32).min(0xFF) << shift;
+    channel(16) | channel(8) | channel(0)
+}
+
+fn mix(a: u32, b: u32, t: f64) -> u32 {
+    if t <= 0.0 {
+        return a;
+    }
+    let channel = |shift: u32| {
+        let from = ((a >> shift) & 0xFF) as f64;
+        let to = ((b >> shift) & 0xFF) as f64;
+        ((from + (to - from) * t).round() as u32).min(0xFF) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn foggy(falloff: f64) -> Shading {
+        Shading { fog: Some(Fog { color: 0x204060, start: 4.0, end: 12.0, falloff }), side_shade: 0.5 }
+    }
+
+    #[test]
+    fn fog_grows_between_start_and_end() {
+        assert_eq!(Shading::default().fog_amount(100.0), 0.0);
+        let linear = foggy(1.0);
+        assert_eq!(linear.fog_amount(2.0), 0.0);
+        assert_eq!(linear.fog_amount(4.0), 0.0);
+        assert_eq!(linear.fog_amount(8.0), 0.5);
+        assert_eq!(linear.fog_amount(12.0), 1.0);
+        assert_eq!(linear.fog_amount(30.0), 1.0);
+        assert_eq!(foggy(2.0).fog_amount(8.0), 0.25);
+    }
+
+    #[test]
+    fn fog_mixes_towards_its_color() {
+        let shading = foggy(1.0);
+        assert_eq!(shading.apply_fog(0xFFFFFF, 1.0), 0xFFFFFF);
+        assert_eq!(shading.apply_fog(0xFFFFFF, 20.0), 0x204060);
+        assert_eq!(shading.apply_fog(0x000000, 8.0), 0x102030);
+    }
+
+    #[test]
+    fn only_north_south_wall_faces_are_darkened() {
+        let shading = Shading { fog: None, side_shade: 0.5 };
+        assert_eq!(shading.shade_wall(0x80C0FF, 1.0, false), 0x80C0FF);
+        assert_eq!(shading.shade_wall(0x80C0FF, 1.0, true), 0x40607F);
+        assert_eq!(Shading::default().shade_wall(0x80C0FF, 1.0, true), 0x80C0FF);
+
+        // La cara se oscurece antes de mezclarla con la niebla
+        assert_eq!(foggy(1.0).shade_wall(0x000000, 20.0, true), 0x204060);
+    }
+}
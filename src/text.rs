@@ -0,0 +1,330 @@
+use crate::framebuffer::Framebuffer;
+
+// Fuente de 5x8 píxeles con todos los caracteres ASCII imprimibles, del espacio (0x20) a
+// `~` (0x7E). Cada glifo es una fila por byte, con el bit 4 a la izquierda; las mayúsculas
+// ocupan las 7 primeras filas y la última es para los trazos que bajan de la línea (g, j, p...)
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 8;
+const CHAR_SPACING: usize = 1; // Separación entre caracteres, en píxeles sin escalar
+const LINE_SPACING: usize = 2; // Separación entre líneas
+
+const FIRST_CHAR: char = ' ';
+
+const FONT: [[u8; GLYPH_HEIGHT]; 95] = [
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // espacio
+    [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100, 0b00000], // !
+    [0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // "
+    [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010, 0b00000], // #
+    [0b00100, 0b01111, 0b10100, 0b01110, 0b00101, 0b11110, 0b00100, 0b00000], // $
+    [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011, 0b00000], // %
+    [0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101, 0b00000], // &
+    [0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // '
+    [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010, 0b00000], // (
+    [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000, 0b00000], // )
+    [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000, 0b00000], // *
+    [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000, 0b00000], // +
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00100, 0b00100, 0b01000], // ,
+    [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000, 0b00000], // -
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100, 0b00000], // .
+    [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000, 0b00000], // /
+    [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110, 0b00000], // 0
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110, 0b00000], // 1
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111, 0b00000], // 2
+    [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110, 0b00000], // 3
+    [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010, 0b00000], // 4
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110, 0b00000], // 5
+    [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110, 0b00000], // 6
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00000], // 7
+    [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110, 0b00000], // 8
+    [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100, 0b00000], // 9
+    [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000, 0b00000], // :
+    [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00100, 0b01000], // ;
+    [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00000], // <
+    [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000], // =
+    [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000, 0b00000], // >
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100, 0b00000], // ?
+    [0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110, 0b00000], // @
+    [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001, 0b00000], // A
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110, 0b00000], // B
+    [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110, 0b00000], // C
+    [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100, 0b00000], // D
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111, 0b00000], // E
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000, 0b00000], // F
+    [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111, 0b00000], // G
+    [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001, 0b00000], // H
+    [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110, 0b00000], // I
+    [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100, 0b00000], // J
+    [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001, 0b00000], // K
+    [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111, 0b00000], // L
+    [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001, 0b00000], // M
+    [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001, 0b00000], // N
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110, 0b00000], // O
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000, 0b00000], // P
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101, 0b00000], // Q
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001, 0b00000], // R
+    [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110, 0b00000], // S
+    [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000], // T
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110, 0b00000], // U
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00000], // V
+    [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010, 0b00000], // W
+    [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001, 0b00000], // X
+    [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000], // Y
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111, 0b00000], // Z
+    [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110, 0b00000], // [
+    [0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000, 0b00000], // \
+    [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110, 0b00000], // ]
+    [0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // ^
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111], // _
+    [0b01000, 0b00100, 0b00010, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // `
+    [0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111, 0b00000], // a
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110, 0b00000], // b
+    [0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110, 0b00000], // c
+    [0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111, 0b00000], // d
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110, 0b00000], // e
+    [0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000, 0b00000], // f
+    [0b00000, 0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110], // g
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001, 0b00000], // h
+    [0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110, 0b00000], // i
+    [0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100], // j
+    [0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b00000], // k
+    [0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110, 0b00000], // l
+    [0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10101, 0b10101, 0b00000], // m
+    [0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001, 0b00000], // n
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110, 0b00000], // o
+    [0b00000, 0b00000, 0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000], // p
+    [0b00000, 0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b00001], // q
+    [0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000, 0b00000], // r
+    [0b00000, 0b00000, 0b01111, 0b10000, 0b01110, 0b00001, 0b11110, 0b00000], // s
+    [0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110, 0b00000], // t
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101, 0b00000], // u
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00000], // v
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010, 0b00000], // w
+    [0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b00000], // x
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110], // y
+    [0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111, 0b00000], // z
+    [0b00010, 0b00100, 0b00100, 0b01000, 0b00100, 0b00100, 0b00010, 0b00000], // {
+    [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000], // |
+    [0b01000, 0b00100, 0b00100, 0b00010, 0b00100, 0b00100, 0b01000, 0b00000], // }
+    [0b00000, 0b00000, 0b01000, 0b10101, 0b00010, 0b00000, 0b00000, 0b00000], // ~
+];
+
+// Se dibuja en lugar de los caracteres que no están en la fuente
+const REPLACEMENT: [u8; GLYPH_HEIGHT] = [0b11111, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11111, 0b00000];
+
+fn glyph(ch: char) -> &'static [u8; GLYPH_HEIGHT] {
+    let index = (ch as usize).wrapping_sub(FIRST_CHAR as usize);
+    FONT.get(index).unwrap_or(&REPLACEMENT)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// Cómo se dibuja un texto. Con `Align::Center` la x indica el centro del texto y con
+// `Align::Right` su borde derecho; cada línea se alinea por separado.
+#[derive(Clone, Copy, Debug)]
+pub struct TextStyle {
+    pub color: u32,
+    pub scale: usize,
+    pub align: Align,
+    pub shadow: Option<u32>, // Color de la sombra, desplazada un píxel escalado abajo a la derecha
+}
+
+impl TextStyle {
+    pub fn new(color: u32, scale: usize) -> Self {
+        Self { color, scale, align: Align::Left, shadow: None }
+    }
+
+    pub fn line_height(&self) -> usize {
+        (GLYPH_HEIGHT + LINE_SPACING) * self.scale
+    }
+}
+
+// Ancho en píxeles de una línea de texto, sin la separación tras el último carácter
+pub fn line_width(line: &str, scale: usize) -> usize {
+    let chars = line.chars().count();
+    (chars * (GLYPH_WIDTH + CHAR_SPACING)).saturating_sub(CHAR_SPACING) * scale
+}
+
+// Ancho y alto en píxeles de un texto que puede tener varias líneas separadas por '\n'
+pub fn measure_text(text: &str, scale: usize) -> (usize, usize) {
+    let width = text.lines().map(|line| line_width(line, scale)).max().unwrap_or(0);
+    let lines = text.lines().count();
+    let height = (lines * (GLYPH_HEIGHT + LINE_SPACING)).saturating_sub(LINE_SPACING) * scale;
+    (width, height)
+}
+
+// Parte el texto en líneas que no pasen de `max_width` píxeles, cortando por los espacios.
+// Las palabras más largas que una línea se parten donde haga falta.
+pub fn wrap_text(text: &str, max_width: usize, scale: usize) -> Vec<String> {
+    let max_chars = ((max_width / scale + CHAR_SPACING) / (GLYPH_WIDTH + CHAR_SPACING)).max(1);
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut line_chars = 0;
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            // Hueco para la palabra, contando el espacio que la separa de la anterior
+            if line_chars > 0 && line_chars + 1 + word.len() > max_chars {
+                lines.push(std::mem::take(&mut line));
+                line_chars = 0;
+            }
+            while word.len() > max_chars {
+                let rest = word.split_off(max_chars);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+            if word.is_empty() {
+                continue;
+            }
+            if line_chars > 0 {
+                line.push(' ');
+                line_chars += 1;
+            }
+            line_chars += word.len();
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn draw_glyph(framebuffer: &mut Framebuffer, x: usize, y: usize, ch: char, color: u32, scale: usize) {
+    for (row, bits) in glyph(ch).iter().enumerate() {
+        for col in 0..GLYPH_WIDTH {
+            if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                for sy in 0..scale {
+                    for sx in 0..scale {
+                        framebuffer.point(x + col * scale + sx, y + row * scale + sy, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn draw_line(framebuffer: &mut Framebuffer, x: usize, y: usize, line: &str, color: u32, scale: usize) {
+    let advance = (GLYPH_WIDTH + CHAR_SPACING) * scale;
+    for (i, ch) in line.chars().enumerate() {
+        if ch != ' ' {
+            draw_glyph(framebuffer, x + i * advance, y, ch, color, scale);
+        }
+    }
+}
+
+// Dibuja un texto de una o varias líneas con su estilo; la y es la parte de arriba
+pub fn draw_text_styled(framebuffer: &mut Framebuffer, x: usize, y: usize, text: &str, style: &TextStyle) {
+    for (i, line) in text.lines().enumerate() {
+        let width = line_width(line, style.scale);
+        let line_x = match style.align {
+            Align::Left => x,
+            Align::Center => x.saturating_sub(width / 2),
+            Align::Right => x.saturating_sub(width),
+        };
+        let line_y = y + i * style.line_height();
+
+        if let Some(shadow) = style.shadow {
+            draw_line(framebuffer, line_x + style.scale, line_y + style.scale, line, shadow, style.scale);
+        }
+        draw_line(framebuffer, line_x, line_y, line, style.color, style.scale);
+    }
+}
+
+// Dibuja el texto partido en líneas para que quepa en `width` píxeles a partir de x, alineado
+// dentro de ese ancho. Devuelve la altura que ocupa.
+pub fn draw_wrapped_text(
+    framebuffer: &mut Framebuffer,
+    x: usize,
+    y: usize,
+    width: usize,
+    text: &str,
+    style: &TextStyle,
+) -> usize {
+    let anchor = match style.align {
+        Align::Left => x,
+        Align::Center => x + width / 2,
+        Align::Right => x + width,
+    };
+    let lines = wrap_text(text, width, style.scale).join("\n");
+    draw_text_styled(framebuffer, anchor, y, &lines, style);
+    measure_text(&lines, style.scale).1
+}
+
+pub fn draw_text(framebuffer: &mut Framebuffer, x: usize, y: usize, text: &str, color: u32, scale: usize) {
+    draw_text_styled(framebuffer, x, y, text, &TextStyle::new(color, scale));
+}
+
+// Centra el texto en el framebuffer, en horizontal y en vertical
+pub fn draw_centered_text(framebuffer: &mut Framebuffer, text: &str, color: u32, scale: usize) {
+    let (_, height) = measure_text(text, scale);
+    let y = framebuffer.height.saturating_sub(height) / 2;
+    let mut style = TextStyle::new(color, scale);
+    style.align = Align::Center;
+    draw_text_styled(framebuffer, framebuffer.width / 2, y, text, &style);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Con escala 1 caben 5 caracteres en 29 píxeles: 5 * 5 + 4 de separación
+    const FIVE_CHARS: usize = 29;
+
+    #[test]
+    fn wrap_breaks_at_spaces_within_the_width() {
+        assert_eq!(wrap_text("ab cd ef", FIVE_CHARS, 1), ["ab cd", "ef"]);
+        assert_eq!(wrap_text("ab cd ef", FIVE_CHARS * 2 + 2, 2), ["ab cd", "ef"]);
+        for line in wrap_text("the quick brown fox jumps over the lazy dog", FIVE_CHARS * 2, 1) {
+            assert!(line_width(&line, 1) <= FIVE_CHARS * 2, "`{}` is too wide", line);
+        }
+    }
+
+    #[test]
+    fn wrap_splits_words_longer_than_a_line() {
+        assert_eq!(wrap_text("ab abcdefghijkl", FIVE_CHARS, 1), ["ab", "abcde", "fghij", "kl"]);
+        assert_eq!(wrap_text("abcdefghij", FIVE_CHARS, 1), ["abcde", "fghij"]);
+    }
+
+    #[test]
+    fn wrap_keeps_explicit_newlines() {
+        assert_eq!(wrap_text("ab\n\ncd ef gh", FIVE_CHARS, 1), ["ab", "", "cd ef", "gh"]);
+    }
+
+    #[test]
+    fn measure_counts_every_line() {
+        assert_eq!(measure_text("ab\nabcd", 2), ((4 * 6 - 1) * 2, (2 * 10 - 2) * 2));
+        assert_eq!(measure_text("", 1), (0, 0));
+    }
+
+    // Columnas con algún píxel encendido tras dibujar `H`, que ocupa las 5 columnas del glifo
+    fn lit_columns(align: Align) -> (usize, usize) {
+        let mut framebuffer = Framebuffer::new(100, 10);
+        let mut style = TextStyle::new(0xFFFFFF, 1);
+        style.align = align;
+        draw_text_styled(&mut framebuffer, 50, 0, "H", &style);
+        let lit: Vec<usize> = (0..100).filter(|&x| (0..10).any(|y| framebuffer.is_point_set(x, y))).collect();
+        (lit[0], lit[lit.len() - 1])
+    }
+
+    #[test]
+    fn alignment_moves_the_line() {
+        assert_eq!(lit_columns(Align::Left), (50, 54));
+        assert_eq!(lit_columns(Align::Center), (48, 52));
+        assert_eq!(lit_columns(Align::Right), (45, 49));
+    }
+
+    #[test]
+    fn characters_outside_the_font_use_the_replacement_glyph() {
+        for ch in ['é', '\t', '\u{7f}', '€'] {
+            assert_eq!(glyph(ch), &REPLACEMENT);
+        }
+        assert_eq!(glyph('~'), &FONT[FONT.len() - 1]);
+        // Cuentan como un carácter aunque ocupen varios bytes
+        assert_eq!(line_width("é€", 1), 11);
+        assert_eq!(wrap_text("ééééééé", FIVE_CHARS, 1), ["ééééé", "éé"]);
+    }
+}
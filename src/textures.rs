@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use std::path::Path;
+
+use image::{DynamicImage, ImageResult};
+
+use crate::map::Map;
+
+const DEFAULT_WALL_TEXTURE: &str = "src/wall_texture.png";
+
+// Imagen decodificada una sola vez a colores 0xAARRGGBB, guardada por columnas para que
+// recorrer una columna de pared o de sprite lea memoria seguida
+pub struct Texture {
+    width: u32,
+    height: u32,
+    pixels: Vec<u32>,
+    // Máscaras para las dimensiones potencia de dos, que se ajustan con un `&` en vez de comparar
+    width_mask: Option<u32>,
+    height_mask: Option<u32>,
+}
+
+impl Texture {
+    pub fn open<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        Ok(Self::from_image(&image::open(path)?))
+    }
+
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let image = image.to_rgba8();
+        let (width, height) = image.dimensions();
+
+        let mut pixels = vec![0; (width * height) as usize];
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let [r, g, b, a] = pixel.0;
+            pixels[(x * height + y) as usize] =
+                ((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32);
+        }
+
+        let mask = |size: u32| size.is_power_of_two().then(|| size - 1);
+        Self {
+            width,
+            height,
+            pixels,
+            width_mask: mask(width),
+            height_mask: mask(height),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Todos los píxeles de una columna, de arriba a abajo
+    pub fn column(&self, x: u32) -> &[u32] {
+        let start = (x * self.height) as usize;
+        &self.pixels[start..start + self.height as usize]
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u32 {
+        self.pixels[(x * self.height + y) as usize]
+    }
+
+    // Columna para una coordenada horizontal entre 0 y 1
+    pub fn texel_x(&self, u: f64) -> u32 {
+        let x = (u * self.width as f64) as u32;
+        match self.width_mask {
+            Some(mask) => x & mask,
+            None => x.min(self.width - 1),
+        }
+    }
+
+    // Fila para una coordenada vertical entre 0 y 1
+    pub fn texel_y(&self, v: f64) -> u32 {
+        let y = (v * self.height as f64) as u32;
+        match self.height_mask {
+            Some(mask) => y & mask,
+            None => y.min(self.height - 1),
+        }
+    }
+}
+
+// Color sin el canal alfa
+pub fn rgb(texel: u32) -> u32 {
+    texel & 0xFFFFFF
+}
+
+pub fn alpha(texel: u32) -> u8 {
+    (texel >> 24) as u8
+}
+
+// Texturas indexadas por el tipo de celda del mapa (paredes, suelo y techo)
+pub struct WallTextures {
+    default: Texture,
+    textures: HashMap<i32, Texture>,
+}
+
+impl WallTextures {
+    pub fn load(map: &Map) -> ImageResult<Self> {
+        let default = Texture::open(DEFAULT_WALL_TEXTURE)?;
+
+        let mut textures = HashMap::new();
+        for (&cell, path) in &map.textures {
+            textures.insert(cell, Texture::open(path)?);
+        }
+
+        Ok(Self { default, textures })
+    }
+
+    // Los tipos de celda sin textura propia usan la textura de pared por defecto
+    pub fn get(&self, cell: i32) -> &Texture {
+        self.textures.get(&cell).unwrap_or(&self.default)
+    }
+}
+
+// Carga la imagen de un sprite conservando su canal alfa
+pub fn load_sprite<P: AsRef<Path>>(path: P) -> ImageResult<Texture> {
+    Texture::open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    // Cada píxel lleva codificada su posición para poder comprobar de dónde sale
+    fn image(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| Rgba([x as u8, y as u8, 7, 255]))
+    }
+
+    fn texture(width: u32, height: u32) -> Texture {
+        Texture::from_image(&DynamicImage::ImageRgba8(image(width, height)))
+    }
+
+    #[test]
+    fn get_and_column_match_the_image() {
+        let source = image(8, 6);
+        let texture = texture(8, 6);
+        for x in 0..8 {
+            let column: Vec<u32> = (0..6)
+                .map(|y| {
+                    let [r, g, b, a] = source.get_pixel(x, y).0;
+                    ((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32)
+                })
+                .collect();
+            assert_eq!(texture.column(x), column);
+            for y in 0..6 {
+                assert_eq!(texture.get(x, y), column[y as usize]);
+            }
+        }
+        assert_eq!(rgb(texture.get(5, 3)), 0x050307);
+        assert_eq!(alpha(texture.get(5, 3)), 255);
+    }
+
+    #[test]
+    fn power_of_two_sizes_wrap() {
+        let texture = texture(8, 4);
+        assert_eq!(texture.texel_x(0.0), 0);
+        assert_eq!(texture.texel_x(0.99), 7);
+        assert_eq!(texture.texel_x(1.0), 0);
+        assert_eq!(texture.texel_x(1.25), 2);
+        assert_eq!(texture.texel_y(0.5), 2);
+        assert_eq!(texture.texel_y(2.75), 3);
+    }
+
+    #[test]
+    fn other_sizes_are_clamped() {
+        let texture = texture(6, 5);
+        assert_eq!(texture.texel_x(0.5), 3);
+        assert_eq!(texture.texel_x(1.0), 5);
+        assert_eq!(texture.texel_x(1.5), 5);
+        assert_eq!(texture.texel_y(0.99), 4);
+        assert_eq!(texture.texel_y(3.0), 4);
+    }
+}